@@ -0,0 +1,269 @@
+//! Project defaults read from `.composerize.toml`.
+//!
+//! The file is looked up in the current directory first, then in
+//! `$XDG_CONFIG_HOME/composerize-np/config.toml` (`~/.config/...` when the
+//! variable is unset). Every key is optional; command-line flags override it.
+//!
+//! ```toml
+//! format = "v3x"
+//! env-style = "map"
+//! secret-patterns = ["*PASSWORD*", "*TOKEN*"]
+//!
+//! [flags.team]
+//! type = "array"
+//! path = "labels"
+//! ```
+
+use crate::mappings::{FlagMappings, MappingEntry};
+use crate::naming::NamingPolicy;
+use crate::options::{
+    ComposeVersion, ComposerizeOptions, EnvStyle, OutputFormat, PortStyle, ResourcePolicy, Strictness,
+};
+use crate::parser::CommandStyle;
+use crate::shell::ShellDialect;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the project config file
+pub const CONFIG_FILE_NAME: &str = ".composerize.toml";
+
+/// Conversion settings from a config file or the command line
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ComposeVersion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<OutputFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indent: Option<usize>,
+    /// `container-name` or `image`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub naming: Option<NamingPolicy>,
+    /// Fixed service name; takes precedence over `naming`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_style: Option<EnvStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port_style: Option<PortStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command_style: Option<CommandStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<ShellDialect>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret_patterns: Option<Vec<String>>,
+    /// Add `depends_on` for environment values naming another service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub infer_depends_on: Option<bool>,
+    /// `auto`, `external` or `managed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_policy: Option<ResourcePolicy>,
+    /// `auto`, `external` or `managed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume_policy: Option<ResourcePolicy>,
+    /// Mappings file, relative to the config file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mappings: Option<PathBuf>,
+    /// Inline flag mappings, applied after the mappings file
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub flags: IndexMap<String, MappingEntry>,
+}
+
+impl Config {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| format!("Failed to parse config: {}", e))
+    }
+
+    /// Reads a config file, resolving `mappings` against its directory
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+        let mut config = Self::parse(&content).map_err(|e| format!("{} ({})", e, path.display()))?;
+        if let (Some(mappings), Some(dir)) = (&config.mappings, path.parent()) {
+            config.mappings = Some(dir.join(mappings));
+        }
+        Ok(config)
+    }
+
+    /// Finds the config file that applies to the current directory
+    pub fn discover() -> Option<PathBuf> {
+        let local = PathBuf::from(CONFIG_FILE_NAME);
+        if local.is_file() {
+            return Some(local);
+        }
+
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        let global = config_home.join("composerize-np").join("config.toml");
+        global.is_file().then_some(global)
+    }
+
+    /// Returns `self` with every setting given in `other` replaced
+    pub fn merge(self, other: Config) -> Config {
+        let mut flags = self.flags;
+        flags.extend(other.flags);
+        Config {
+            format: other.format.or(self.format),
+            output_format: other.output_format.or(self.output_format),
+            indent: other.indent.or(self.indent),
+            naming: other.naming.or(self.naming),
+            service_name: other.service_name.or(self.service_name),
+            env_style: other.env_style.or(self.env_style),
+            port_style: other.port_style.or(self.port_style),
+            command_style: other.command_style.or(self.command_style),
+            shell: other.shell.or(self.shell),
+            strict: other.strict.or(self.strict),
+            secret_patterns: other.secret_patterns.or(self.secret_patterns),
+            infer_depends_on: other.infer_depends_on.or(self.infer_depends_on),
+            network_policy: other.network_policy.or(self.network_policy),
+            volume_policy: other.volume_policy.or(self.volume_policy),
+            mappings: other.mappings.or(self.mappings),
+            flags,
+        }
+    }
+
+    /// Fills every unset setting with its default
+    pub fn effective(&self) -> Config {
+        let defaults = ComposerizeOptions::default();
+        Config {
+            format: Some(self.format.unwrap_or(defaults.version)),
+            output_format: Some(self.output_format.unwrap_or(defaults.output_format)),
+            indent: Some(self.indent.unwrap_or(defaults.indent)),
+            naming: Some(self.naming.clone().unwrap_or(defaults.naming)),
+            service_name: self.service_name.clone(),
+            env_style: Some(self.env_style.unwrap_or(defaults.env_style)),
+            port_style: Some(self.port_style.unwrap_or(defaults.port_style)),
+            command_style: Some(self.command_style.unwrap_or(defaults.command_style)),
+            shell: Some(self.shell.unwrap_or(defaults.dialect)),
+            strict: Some(self.strict.unwrap_or(false)),
+            secret_patterns: Some(self.secret_patterns.clone().unwrap_or(defaults.secret_patterns)),
+            infer_depends_on: Some(self.infer_depends_on.unwrap_or(defaults.infer_depends_on)),
+            network_policy: Some(self.network_policy.unwrap_or(defaults.network_policy)),
+            volume_policy: Some(self.volume_policy.unwrap_or(defaults.volume_policy)),
+            mappings: self.mappings.clone(),
+            flags: self.flags.clone(),
+        }
+    }
+
+    /// Builds conversion options, loading the mappings file if one is set
+    pub fn to_options(&self) -> Result<ComposerizeOptions, String> {
+        let config = self.effective();
+
+        let mut mappings = match &config.mappings {
+            Some(path) => FlagMappings::load(path)?,
+            None => FlagMappings::new(),
+        };
+        mappings.extend(config.flags)?;
+
+        let naming = match config.service_name {
+            Some(name) => NamingPolicy::Fixed(name),
+            None => config.naming.unwrap_or_default(),
+        };
+        let strictness = if config.strict == Some(true) {
+            Strictness::Strict
+        } else {
+            Strictness::Lenient
+        };
+
+        Ok(ComposerizeOptions::new()
+            .with_version(config.format.unwrap_or_default())
+            .with_output_format(config.output_format.unwrap_or_default())
+            .with_indent(config.indent.unwrap_or(2))
+            .with_naming(naming)
+            .with_env_style(config.env_style.unwrap_or_default())
+            .with_port_style(config.port_style.unwrap_or_default())
+            .with_command_style(config.command_style.unwrap_or_default())
+            .with_dialect(config.shell.unwrap_or_default())
+            .with_strictness(strictness)
+            .with_secret_patterns(config.secret_patterns.unwrap_or_default())
+            .with_infer_depends_on(config.infer_depends_on.unwrap_or(true))
+            .with_network_policy(config.network_policy.unwrap_or_default())
+            .with_volume_policy(config.volume_policy.unwrap_or_default())
+            .with_mappings(mappings))
+    }
+
+    /// Renders the settings as a config file
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string(self).map_err(|e| format!("Failed to serialize config: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_options() {
+        let config = Config::parse(
+            r#"
+            format = "v3x"
+            indent = 4
+            naming = "image"
+            env-style = "map"
+            port-style = "long"
+            shell = "pwsh"
+            secret-patterns = ["*PASSWORD*"]
+            infer-depends-on = false
+            network-policy = "managed"
+            volume-policy = "external"
+
+            [flags.team]
+            type = "array"
+            path = "labels"
+            "#,
+        )
+        .unwrap();
+
+        let options = config.to_options().unwrap();
+        assert_eq!(options.version, ComposeVersion::V3);
+        assert_eq!(options.indent, 4);
+        assert_eq!(options.naming, NamingPolicy::Image);
+        assert_eq!(options.env_style, EnvStyle::Map);
+        assert_eq!(options.port_style, PortStyle::Long);
+        assert_eq!(options.dialect, ShellDialect::PowerShell);
+        assert_eq!(options.secret_patterns, vec!["*PASSWORD*"]);
+        assert!(!options.infer_depends_on);
+        assert_eq!(options.network_policy, ResourcePolicy::Managed);
+        assert_eq!(options.volume_policy, ResourcePolicy::External);
+        assert_eq!(options.mappings.get("team").unwrap().path, "labels");
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys_and_values() {
+        assert!(Config::parse("fromat = \"v3x\"").is_err());
+        assert!(Config::parse("format = \"v4x\"").is_err());
+        assert!(Config::parse("naming = \"fixed\"").is_err());
+    }
+
+    #[test]
+    fn test_merge_prefers_other() {
+        let file = Config::parse("format = \"v3x\"\nindent = 4\nservice-name = \"web\"").unwrap();
+        let cli = Config {
+            indent: Some(2),
+            env_style: Some(EnvStyle::Map),
+            ..Config::default()
+        };
+        let merged = file.merge(cli);
+        assert_eq!(merged.format, Some(ComposeVersion::V3));
+        assert_eq!(merged.indent, Some(2));
+        assert_eq!(merged.env_style, Some(EnvStyle::Map));
+        assert_eq!(merged.to_options().unwrap().naming, NamingPolicy::Fixed("web".to_string()));
+    }
+
+    #[test]
+    fn test_effective_round_trips() {
+        let effective = Config::default().effective();
+        let toml = effective.to_toml().unwrap();
+        assert!(toml.contains("format = \"latest\""));
+        assert!(toml.contains("naming = \"container-name\""));
+        assert_eq!(Config::parse(&toml).unwrap(), effective);
+    }
+}
@@ -0,0 +1,293 @@
+//! Trace of how each flag of a command ends up in the compose file.
+
+use crate::mappings::ArgType;
+use crate::options::{ComposerizeOptions, Strictness};
+use crate::parser::{self, ParsedCommand, StackCommand};
+use crate::stack::Dependency;
+use crate::Composerizer;
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// Where one flag value went
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceEntry {
+    /// Flag as written, e.g. `-p` or `--publish`
+    pub flag: String,
+    pub arg_type: ArgType,
+    /// Path of the key the value was written to, with `¤network¤` resolved
+    pub path: String,
+    /// Value given on the command line
+    pub value: String,
+    /// What was written at `path`
+    pub result: Value,
+}
+
+/// The flag-to-key trace of a command
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explanation {
+    pub image: String,
+    pub command: Vec<String>,
+    pub entries: Vec<TraceEntry>,
+    /// Known flags without a compose equivalent
+    pub ignored: Vec<String>,
+    /// Flags missing from the mapping table
+    pub unmapped: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// The traces of every command in a stack and the `depends_on` edges
+/// added between their services
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StackExplanation {
+    pub commands: Vec<Explanation>,
+    pub dependencies: Vec<Dependency>,
+}
+
+/// Parses `input` and traces every flag value through its mapping
+pub fn explain(input: &str, options: &ComposerizeOptions) -> Result<Explanation, String> {
+    explain_parsed(parser::parse_command(input, &options.parse_options())?, options)
+}
+
+/// Like [`explain`] for one or more commands, also reporting why each
+/// `depends_on` edge was added
+pub fn explain_stack(input: &str, options: &ComposerizeOptions) -> Result<StackExplanation, String> {
+    let commands = parser::parse_stack(input, &options.parse_options())?
+        .into_iter()
+        .filter_map(|command| match command {
+            StackCommand::Run(parsed) => Some(explain_parsed(parsed, options)),
+            StackCommand::CreateNetwork(_) | StackCommand::CreateVolume(_) => None,
+        })
+        .collect::<Result<_, _>>()?;
+
+    // Warnings are part of each trace, so they must not fail the conversion
+    let mut lenient = options.clone().with_strictness(Strictness::Lenient);
+    lenient.merge_target = None;
+    let dependencies = Composerizer::new(lenient).convert(input)?.dependencies;
+
+    Ok(StackExplanation { commands, dependencies })
+}
+
+fn explain_parsed(parsed: ParsedCommand, options: &ComposerizeOptions) -> Result<Explanation, String> {
+    let network = parser::primary_network(&parsed.args);
+
+    let mut entries = Vec::new();
+    let mut ignored = Vec::new();
+    let mut unmapped = Vec::new();
+
+    for (name, values) in &parsed.args {
+        let flag = display_flag(name);
+        let mapping = match options.mappings.get(name) {
+            Some(mapping) if mapping.path.is_empty() => {
+                ignored.push(flag);
+                continue;
+            }
+            Some(mapping) => mapping,
+            None => {
+                unmapped.push(flag);
+                continue;
+            }
+        };
+
+        let path = mapping.path.replace("¤network¤", &network);
+        for value in values {
+            // Apply the value on its own to see exactly what it produces;
+            // some types write elsewhere than their path, e.g. --mount
+            let mut fragment = serde_yaml::Mapping::new();
+            parser::apply_mapping(&mut fragment, &path, value, &mapping.arg_type)?;
+            let (path, result) = resolve(fragment);
+            entries.push(TraceEntry {
+                flag: flag.clone(),
+                arg_type: mapping.arg_type.clone(),
+                path,
+                value: value.clone(),
+                result,
+            });
+        }
+    }
+
+    Ok(Explanation {
+        image: parsed.image,
+        command: parsed.command,
+        entries,
+        ignored,
+        unmapped,
+        warnings: parsed.warnings,
+    })
+}
+
+/// Writes a flag name back with its dashes
+pub fn display_flag(name: &str) -> String {
+    if name.chars().count() == 1 {
+        format!("-{}", name)
+    } else {
+        format!("--{}", name)
+    }
+}
+
+/// Follows single-key mappings down to the value that was written
+fn resolve(fragment: serde_yaml::Mapping) -> (String, Value) {
+    let mut path = Vec::new();
+    let mut value = Value::Mapping(fragment);
+    loop {
+        match value {
+            Value::Mapping(map) if map.len() == 1 => {
+                let (key, inner) = map.into_iter().next().unwrap_or_default();
+                path.push(key.as_str().unwrap_or_default().to_string());
+                value = inner;
+            }
+            other => return (path.join("/"), other),
+        }
+    }
+}
+
+impl Explanation {
+    /// Renders the trace as aligned text
+    pub fn to_text(&self) -> String {
+        let mut out = format!("Image: {}\n", self.image);
+        if !self.command.is_empty() {
+            out.push_str(&format!("Command: {}\n", self.command.join(" ")));
+        }
+
+        let width = |f: fn(&TraceEntry) -> usize| self.entries.iter().map(f).max().unwrap_or(0);
+        let flag_width = width(|e| e.flag.len() + 1 + e.value.chars().count());
+        let type_width = width(|e| format!("{:?}", e.arg_type).len());
+        let path_width = width(|e| e.path.len());
+        for entry in &self.entries {
+            let result = serde_json::to_string(&entry.result).unwrap_or_default();
+            out.push_str(&format!(
+                "  {:<fw$}  {:<tw$}  {:<pw$}  = {}\n",
+                format!("{} {}", entry.flag, entry.value),
+                format!("{:?}", entry.arg_type),
+                entry.path,
+                result,
+                fw = flag_width,
+                tw = type_width,
+                pw = path_width,
+            ));
+        }
+
+        if !self.ignored.is_empty() {
+            out.push_str(&format!("Ignored: {}\n", self.ignored.join(", ")));
+        }
+        if !self.unmapped.is_empty() {
+            out.push_str(&format!("Unmapped: {}\n", self.unmapped.join(", ")));
+        }
+        for warning in &self.warnings {
+            out.push_str(&format!("Warning: {}\n", warning));
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize JSON: {}", e))
+    }
+}
+
+impl StackExplanation {
+    /// Renders each command's trace, then the dependencies
+    pub fn to_text(&self) -> String {
+        let mut out = self
+            .commands
+            .iter()
+            .map(Explanation::to_text)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if !self.dependencies.is_empty() {
+            out.push_str("\nDependencies:\n");
+            for dependency in &self.dependencies {
+                let condition = serde_json::to_value(dependency.condition).unwrap_or_default();
+                out.push_str(&format!(
+                    "  {} -> {} ({}): {}\n",
+                    dependency.service,
+                    dependency.target,
+                    condition.as_str().unwrap_or_default(),
+                    dependency.reasons.join("; ")
+                ));
+            }
+        }
+        out
+    }
+
+    /// A single command is rendered like [`Explanation::to_json`]
+    pub fn to_json(&self) -> Result<String, String> {
+        match self.commands.as_slice() {
+            [command] if self.dependencies.is_empty() => command.to_json(),
+            _ => serde_json::to_string_pretty(self).map_err(|e| format!("Failed to serialize JSON: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explain_default(input: &str) -> Explanation {
+        explain(input, &ComposerizeOptions::new()).unwrap()
+    }
+
+    #[test]
+    fn test_trace_entries() {
+        let explanation = explain_default(
+            "docker run -d --rm --network backend --ip 10.0.0.2 -p 80:80 --ulimit nofile=1024:2048 --frob nginx",
+        );
+        let paths: Vec<(&str, &str)> = explanation
+            .entries
+            .iter()
+            .map(|e| (e.flag.as_str(), e.path.as_str()))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                ("--network", "networks/backend"),
+                ("--ip", "networks/backend/ipv4_address"),
+                ("-p", "ports"),
+                ("--ulimit", "ulimits/nofile"),
+            ]
+        );
+        assert_eq!(explanation.entries[1].result, Value::from("10.0.0.2"));
+        assert_eq!(explanation.entries[2].arg_type, ArgType::Array);
+        assert_eq!(explanation.ignored, vec!["-d", "--rm"]);
+        assert_eq!(explanation.unmapped, vec!["--frob"]);
+    }
+
+    #[test]
+    fn test_mount_resolves_to_actual_key() {
+        let explanation = explain_default("docker run --mount type=tmpfs,target=/cache nginx");
+        assert_eq!(explanation.entries[0].path, "tmpfs");
+    }
+
+    #[test]
+    fn test_text_and_json() {
+        let explanation = explain_default("docker run -p 80:80 --rm nginx");
+        let text = explanation.to_text();
+        assert!(text.contains("  -p 80:80  Array  ports  = [\"80:80\"]\n"));
+        assert!(text.contains("Ignored: --rm"));
+
+        let json: serde_json::Value = serde_json::from_str(&explanation.to_json().unwrap()).unwrap();
+        assert_eq!(json["entries"][0]["arg_type"], "array");
+        assert_eq!(json["entries"][0]["path"], "ports");
+        assert_eq!(json["ignored"][0], "--rm");
+    }
+
+    #[test]
+    fn test_stack_dependencies() {
+        let explanation = explain_stack(
+            "docker run --name db --health-cmd pg_isready postgres\n\
+             docker run --name app -e DB_HOST=db --link db:database app",
+            &ComposerizeOptions::new(),
+        )
+        .unwrap();
+        assert_eq!(explanation.commands.len(), 2);
+
+        let text = explanation.to_text();
+        assert!(text.contains("\nImage: app\n"));
+        assert!(text.contains(
+            "Dependencies:\n  app -> db (service_healthy): --link db:database; environment DB_HOST=db\n"
+        ));
+
+        let json: serde_json::Value = serde_json::from_str(&explanation.to_json().unwrap()).unwrap();
+        assert_eq!(json["dependencies"][0]["condition"], "service_healthy");
+        assert_eq!(json["commands"][1]["image"], "app");
+    }
+}
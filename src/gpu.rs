@@ -0,0 +1,238 @@
+//! GPU requests from `--gpus` and CDI device names from `--device`.
+//!
+//! Both become entries of `deploy.resources.reservations.devices`.
+
+use serde_yaml::{Mapping, Value};
+
+/// Driver docker picks for `--gpus` when none is given
+const DEFAULT_DRIVER: &str = "nvidia";
+
+/// Parses a `--gpus` value such as `all`, `2`, `"device=0,2"` or
+/// `count=2,capabilities=compute,utility,driver=nvidia` into a device
+/// reservation.
+///
+/// Like docker, the value is a CSV record, so lists can be quoted. Unquoted
+/// list items after `device=` or `capabilities=` are accepted too.
+pub fn parse_gpu_request(value: &str) -> Result<Value, String> {
+    let mut driver = None;
+    let mut count = None;
+    let mut device_ids: Option<Vec<String>> = None;
+    let mut capabilities: Option<Vec<String>> = None;
+    let mut options = Mapping::new();
+    // Key of the list an unquoted item continues
+    let mut list = None;
+
+    for (field, quoted) in split_csv(value)? {
+        let Some((key, val)) = field.split_once('=') else {
+            match list {
+                Some("device") => device_ids.get_or_insert_with(Vec::new).push(field),
+                Some("capabilities") => capabilities.get_or_insert_with(Vec::new).push(field),
+                _ => set_once(&mut count, parse_count(&field)?, "count")?,
+            }
+            continue;
+        };
+        list = None;
+        match key {
+            "driver" => set_once(&mut driver, val.to_string(), key)?,
+            "count" => set_once(&mut count, parse_count(val)?, key)?,
+            "device" => {
+                set_once(&mut device_ids, split_list(val), key)?;
+                list = (!quoted).then_some("device");
+            }
+            "capabilities" => {
+                set_once(&mut capabilities, split_list(val), key)?;
+                list = (!quoted).then_some("capabilities");
+            }
+            "options" => {
+                for (option, _) in split_csv(val)? {
+                    let (name, option_value) = option.split_once('=').unwrap_or((&option, ""));
+                    options.insert(Value::from(name), Value::from(option_value));
+                }
+            }
+            _ => return Err(format!("Unexpected key '{}' in GPU request '{}'", key, value)),
+        }
+    }
+
+    if count.is_some() && device_ids.is_some() {
+        return Err(format!("GPU request '{}' cannot set both count and device", value));
+    }
+    if count.is_none() && device_ids.is_none() {
+        count = Some(Value::from(1));
+    }
+    // Docker always requests the gpu capability besides the given ones
+    let mut capabilities = capabilities.unwrap_or_default();
+    if !capabilities.iter().any(|c| c == "gpu") {
+        capabilities.push("gpu".to_string());
+    }
+
+    let mut request = Mapping::new();
+    request.insert(Value::from("driver"), Value::from(driver.unwrap_or_else(|| DEFAULT_DRIVER.to_string())));
+    if let Some(count) = count {
+        request.insert(Value::from("count"), count);
+    }
+    if let Some(device_ids) = device_ids {
+        request.insert(Value::from("device_ids"), string_list(device_ids));
+    }
+    request.insert(Value::from("capabilities"), string_list(capabilities));
+    if !options.is_empty() {
+        request.insert(Value::from("options"), Value::Mapping(options));
+    }
+    Ok(Value::Mapping(request))
+}
+
+/// Returns true for a fully qualified CDI name of a GPU, e.g.
+/// `nvidia.com/gpu=all` or `amd.com/gpu=0`
+pub fn is_cdi_gpu(device: &str) -> bool {
+    let Some((kind, name)) = device.split_once('=') else {
+        return false;
+    };
+    let Some((vendor, class)) = kind.split_once('/') else {
+        return false;
+    };
+    vendor.contains('.') && class == "gpu" && !name.is_empty() && !name.contains(['/', ':'])
+}
+
+/// Adds a CDI GPU name to the `cdi` reservation in `devices`, creating it
+/// if needed
+pub fn add_cdi_device(devices: &mut Vec<Value>, device: &str) {
+    let existing = devices.iter_mut().find_map(|entry| match entry {
+        Value::Mapping(entry) if entry.get("driver").and_then(Value::as_str) == Some("cdi") => {
+            entry.get_mut("device_ids").and_then(Value::as_sequence_mut)
+        }
+        _ => None,
+    });
+    match existing {
+        Some(device_ids) => device_ids.push(Value::from(device)),
+        None => {
+            let mut request = Mapping::new();
+            request.insert(Value::from("driver"), Value::from("cdi"));
+            request.insert(Value::from("device_ids"), string_list(vec![device.to_string()]));
+            request.insert(Value::from("capabilities"), string_list(vec!["gpu".to_string()]));
+            devices.push(Value::Mapping(request));
+        }
+    }
+}
+
+fn parse_count(value: &str) -> Result<Value, String> {
+    match value {
+        "all" => Ok(Value::from("all")),
+        _ => value
+            .parse::<u32>()
+            .map(Value::from)
+            .map_err(|_| format!("Invalid GPU count '{}': expected a number or all", value)),
+    }
+}
+
+fn set_once<T>(slot: &mut Option<T>, value: T, key: &str) -> Result<(), String> {
+    if slot.replace(value).is_some() {
+        return Err(format!("GPU request key '{}' can be specified only once", key));
+    }
+    Ok(())
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value.split(',').filter(|item| !item.is_empty()).map(str::to_string).collect()
+}
+
+fn string_list(items: Vec<String>) -> Value {
+    Value::Sequence(items.into_iter().map(Value::String).collect())
+}
+
+/// Splits one CSV record, where a field in double quotes may contain commas
+/// and `""` is a literal quote. Each field comes with whether it was quoted.
+fn split_csv(value: &str) -> Result<Vec<(String, bool)>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = value.chars().peekable();
+    let mut quoted = false;
+    let mut was_quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => {
+                quoted = true;
+                was_quoted = true;
+            }
+            ',' if !quoted => fields.push((std::mem::take(&mut field), std::mem::take(&mut was_quoted))),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("Unterminated quote in '{}'", value));
+    }
+    fields.push((field, was_quoted));
+    Ok(fields.into_iter().filter(|(field, _)| !field.is_empty()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(value: &str) -> Value {
+        parse_gpu_request(value).unwrap()
+    }
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_counts() {
+        assert_eq!(request("all"), yaml("{driver: nvidia, count: all, capabilities: [gpu]}"));
+        assert_eq!(request("2"), yaml("{driver: nvidia, count: 2, capabilities: [gpu]}"));
+        assert_eq!(request("count=all"), yaml("{driver: nvidia, count: all, capabilities: [gpu]}"));
+        assert_eq!(request("driver=amd"), yaml("{driver: amd, count: 1, capabilities: [gpu]}"));
+    }
+
+    #[test]
+    fn test_devices_capabilities_and_options() {
+        assert_eq!(
+            request(r#""device=0,2""#),
+            yaml("{driver: nvidia, device_ids: ['0', '2'], capabilities: [gpu]}")
+        );
+        assert_eq!(
+            request("device=GPU-3a23c669,1"),
+            yaml("{driver: nvidia, device_ids: [GPU-3a23c669, '1'], capabilities: [gpu]}")
+        );
+        assert_eq!(
+            request("count=2,capabilities=compute,utility"),
+            yaml("{driver: nvidia, count: 2, capabilities: [compute, utility, gpu]}")
+        );
+        assert_eq!(
+            request(r#"driver=nvidia,"capabilities=gpu,video",1,"options=a=1,b""#),
+            yaml("{driver: nvidia, count: 1, capabilities: [gpu, video], options: {a: '1', b: ''}}")
+        );
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        let error = |value: &str| parse_gpu_request(value).unwrap_err();
+        assert!(error("many").contains("Invalid GPU count 'many'"));
+        assert!(error("count=1,count=2").contains("'count' can be specified only once"));
+        assert!(error("count=1,device=0").contains("both count and device"));
+        assert!(error("mode=fast").contains("Unexpected key 'mode'"));
+        assert!(error(r#""device=0"#).contains("Unterminated quote"));
+    }
+
+    #[test]
+    fn test_cdi_devices() {
+        assert!(is_cdi_gpu("nvidia.com/gpu=all"));
+        assert!(is_cdi_gpu("amd.com/gpu=0"));
+        assert!(!is_cdi_gpu("/dev/nvidia0"));
+        assert!(!is_cdi_gpu("vendor.com/fpga=0"));
+        assert!(!is_cdi_gpu("/dev/sda:/dev/xvda:rwm"));
+
+        let mut devices = Vec::new();
+        add_cdi_device(&mut devices, "nvidia.com/gpu=0");
+        add_cdi_device(&mut devices, "nvidia.com/gpu=1");
+        assert_eq!(
+            Value::Sequence(devices),
+            yaml("[{driver: cdi, device_ids: [nvidia.com/gpu=0, nvidia.com/gpu=1], capabilities: [gpu]}]")
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Image reference parsing and normalization.
+//!
+//! Follows the grammar of docker's reference package:
+//! `[registry/][namespace/]repository[:tag][@digest]`, where the first path
+//! component is a registry only if it contains `.` or `:` or is `localhost`.
+
+use std::fmt;
+
+/// Registry host names that all refer to Docker Hub
+const DOCKER_HUB_REGISTRIES: &[&str] = &["docker.io", "index.docker.io", "registry-1.docker.io"];
+
+/// A parsed image reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageRef {
+    /// Registry host with optional port, e.g. `ghcr.io` or `localhost:5000`
+    pub registry: Option<String>,
+    /// Path components between registry and repository, e.g. `library`
+    pub namespace: Option<String>,
+    /// Last path component, e.g. `nginx`
+    pub repository: String,
+    pub tag: Option<String>,
+    /// Content digest, e.g. `sha256:...`
+    pub digest: Option<String>,
+}
+
+/// Normalizations applied to the image of a converted command
+#[derive(Debug, Clone, Default)]
+pub struct ImageOptions {
+    /// Warn when the image has no tag or uses `latest` and no digest
+    pub warn_unpinned: bool,
+    /// Drop `docker.io/` and `docker.io/library/` prefixes
+    pub normalize: bool,
+    /// Replace the tag with `${VAR:-tag}` using this variable name
+    pub tag_variable: Option<String>,
+}
+
+impl ImageRef {
+    pub fn parse(reference: &str) -> Result<Self, String> {
+        if reference.is_empty() {
+            return Err("Empty image reference".to_string());
+        }
+
+        let (name, digest) = match reference.split_once('@') {
+            Some((name, digest)) => {
+                if !is_valid_digest(digest) {
+                    return Err(format!("Invalid digest in image reference '{}'", reference));
+                }
+                (name, Some(digest.to_string()))
+            }
+            None => (reference, None),
+        };
+
+        // A colon after the last slash separates the tag
+        let last_slash = name.rfind('/').map(|i| i + 1).unwrap_or(0);
+        let (path, tag) = match find_outside_braces(&name[last_slash..], ':') {
+            Some(i) => (&name[..last_slash + i], Some(name[last_slash + i + 1..].to_string())),
+            None => (name, None),
+        };
+
+        let mut components: Vec<&str> = path.split('/').collect();
+        let registry = if components.len() > 1
+            && (components[0].contains('.') || components[0].contains(':') || components[0] == "localhost")
+        {
+            Some(components.remove(0).to_string())
+        } else {
+            None
+        };
+
+        let repository = components.pop().unwrap_or_default().to_string();
+        let namespace = if components.is_empty() {
+            None
+        } else {
+            Some(components.join("/"))
+        };
+
+        let image = Self {
+            registry,
+            namespace,
+            repository,
+            tag,
+            digest,
+        };
+        image.validate(reference)?;
+        Ok(image)
+    }
+
+    fn validate(&self, reference: &str) -> Result<(), String> {
+        // References built from variables cannot be checked until compose interpolates them
+        if reference.contains('$') {
+            return Ok(());
+        }
+
+        let path_ok = self
+            .namespace
+            .iter()
+            .flat_map(|ns| ns.split('/'))
+            .chain(std::iter::once(self.repository.as_str()))
+            .all(is_valid_path_component);
+        if !path_ok {
+            return Err(format!(
+                "Invalid image reference '{}': repository names must be lowercase",
+                reference
+            ));
+        }
+
+        if let Some(tag) = &self.tag {
+            let valid = tag.len() <= 128
+                && tag.chars().next().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+                && tag.chars().all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c));
+            if !valid {
+                return Err(format!("Invalid tag '{}' in image reference '{}'", tag, reference));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the image is pulled from Docker Hub
+    pub fn is_docker_hub(&self) -> bool {
+        match &self.registry {
+            None => true,
+            Some(registry) => DOCKER_HUB_REGISTRIES.contains(&registry.as_str()),
+        }
+    }
+
+    /// Returns true if the image names a specific version by tag or digest
+    pub fn is_pinned(&self) -> bool {
+        self.digest.is_some() || matches!(&self.tag, Some(tag) if tag != "latest")
+    }
+
+    /// Returns the reference in the short form docker prints, dropping the
+    /// Docker Hub registry and the `library/` namespace of official images
+    pub fn familiar(&self) -> Self {
+        let mut image = self.clone();
+        if image.is_docker_hub() {
+            image.registry = None;
+            if image.namespace.as_deref() == Some("library") {
+                image.namespace = None;
+            }
+        }
+        image
+    }
+}
+
+impl fmt::Display for ImageRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(registry) = &self.registry {
+            write!(f, "{}/", registry)?;
+        }
+        if let Some(namespace) = &self.namespace {
+            write!(f, "{}/", namespace)?;
+        }
+        write!(f, "{}", self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies `options` to an image reference, appending warnings.
+///
+/// References that cannot be parsed are returned unchanged with a warning.
+pub fn normalize_image(reference: &str, options: &ImageOptions, warnings: &mut Vec<String>) -> String {
+    let mut image = match ImageRef::parse(reference) {
+        Ok(image) => image,
+        Err(e) => {
+            warnings.push(e);
+            return reference.to_string();
+        }
+    };
+
+    if options.warn_unpinned && !image.is_pinned() && !reference.contains('$') {
+        warnings.push(format!(
+            "Image '{}' is not pinned to a version; consider a specific tag or digest",
+            reference
+        ));
+    }
+
+    if options.normalize {
+        image = image.familiar();
+    }
+
+    if let (Some(variable), Some(tag)) = (&options.tag_variable, &image.tag) {
+        if !tag.contains('$') {
+            image.tag = Some(format!("${{{}:-{}}}", variable, tag));
+        }
+    }
+
+    image.to_string()
+}
+
+/// Finds `ch` outside `${...}` interpolations, which may contain it themselves
+fn find_outside_braces(text: &str, ch: char) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            c if c == ch && depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn is_valid_path_component(component: &str) -> bool {
+    // [a-z0-9]+ separated by `.`, `_`, `__` or dashes
+    !component.is_empty()
+        && component.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-".contains(c))
+        && component.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && component.ends_with(|c: char| c.is_ascii_alphanumeric())
+}
+
+fn is_valid_digest(digest: &str) -> bool {
+    match digest.split_once(':') {
+        Some((algorithm, hex)) => {
+            !algorithm.is_empty()
+                && algorithm.chars().all(|c| c.is_ascii_alphanumeric() || "+._-".contains(c))
+                && hex.len() >= 32
+                && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn test_parse_components() {
+        let image = ImageRef::parse(&format!("localhost:5000/team/app:1.0@{}", DIGEST)).unwrap();
+        assert_eq!(image.registry.as_deref(), Some("localhost:5000"));
+        assert_eq!(image.namespace.as_deref(), Some("team"));
+        assert_eq!(image.repository, "app");
+        assert_eq!(image.tag.as_deref(), Some("1.0"));
+        assert_eq!(image.digest.as_deref(), Some(DIGEST));
+
+        let image = ImageRef::parse("bitnami/redis").unwrap();
+        assert_eq!(image.registry, None);
+        assert_eq!(image.namespace.as_deref(), Some("bitnami"));
+        assert_eq!(image.tag, None);
+
+        let image = ImageRef::parse("localhost:5000").unwrap();
+        assert_eq!(image.repository, "localhost");
+        assert_eq!(image.tag.as_deref(), Some("5000"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for reference in ["nginx", "nginx:1.25-alpine", "ghcr.io/org/sub/app:v2", "${IMAGE}"] {
+            assert_eq!(ImageRef::parse(reference).unwrap().to_string(), reference);
+        }
+
+        let image = ImageRef::parse("nginx:${TAG:-1.21}").unwrap();
+        assert_eq!(image.repository, "nginx");
+        assert_eq!(image.tag.as_deref(), Some("${TAG:-1.21}"));
+    }
+
+    #[test]
+    fn test_invalid_references() {
+        assert!(ImageRef::parse("Nginx").is_err());
+        assert!(ImageRef::parse("nginx:-bad").is_err());
+        assert!(ImageRef::parse("nginx@sha256:xyz").is_err());
+        assert!(ImageRef::parse("").is_err());
+    }
+
+    #[test]
+    fn test_pinning() {
+        assert!(!ImageRef::parse("nginx").unwrap().is_pinned());
+        assert!(!ImageRef::parse("nginx:latest").unwrap().is_pinned());
+        assert!(ImageRef::parse("nginx:1.25").unwrap().is_pinned());
+        assert!(ImageRef::parse(&format!("nginx@{}", DIGEST)).unwrap().is_pinned());
+    }
+
+    #[test]
+    fn test_normalize_image() {
+        let options = ImageOptions {
+            warn_unpinned: true,
+            normalize: true,
+            tag_variable: Some("TAG".to_string()),
+        };
+        let mut warnings = Vec::new();
+        assert_eq!(normalize_image("docker.io/library/nginx:1.21", &options, &mut warnings), "nginx:${TAG:-1.21}");
+        assert_eq!(normalize_image("index.docker.io/bitnami/redis:7", &options, &mut warnings), "bitnami/redis:${TAG:-7}");
+        assert_eq!(normalize_image("ghcr.io/library/x:1", &options, &mut warnings), "ghcr.io/library/x:${TAG:-1}");
+        assert!(warnings.is_empty());
+
+        assert_eq!(normalize_image("nginx", &options, &mut warnings), "nginx");
+        assert_eq!(normalize_image("nginx:latest", &options, &mut warnings), "nginx:${TAG:-latest}");
+        assert_eq!(warnings.len(), 2);
+    }
+}
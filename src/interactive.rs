@@ -0,0 +1,305 @@
+//! Line-based interactive review of a conversion.
+//!
+//! A [`Session`] reads a docker command, shows how each flag was mapped (see
+//! [`crate::explain`]) and previews the compose output. Lines starting with
+//! `:` change settings or write the file; everything else is (part of) the
+//! command. The session only talks to a reader and a writer, so it runs the
+//! same in a terminal and in tests.
+
+use crate::options::{ComposeVersion, ComposerizeOptions, EnvStyle, OutputFormat, PortStyle};
+use crate::explain::explain_stack;
+use crate::parser::CommandStyle;
+use crate::Composerizer;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+Paste a docker run command, or several separated by ; or && (end a line
+with \\ to continue it), then:
+  :version latest|v2x|v3x   Compose version
+  :env list|map             Environment style
+  :ports short|long         Port syntax
+  :command exec|auto        Command style
+  :format yaml|json         Output format
+  :show                     Show the review again
+  :write [FILE]             Write the compose file (default: docker-compose.yml)
+  :help                     Show this help
+  :quit                     Leave without writing";
+
+/// What the caller should do after a line was handled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Continue,
+    Quit,
+}
+
+/// State of an interactive review
+#[derive(Debug, Clone)]
+pub struct Session {
+    options: ComposerizeOptions,
+    command: Option<String>,
+    /// Lines of a command continued with a trailing backslash
+    pending: Vec<String>,
+}
+
+impl Session {
+    pub fn new(options: ComposerizeOptions) -> Self {
+        Self {
+            options,
+            command: None,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn options(&self) -> &ComposerizeOptions {
+        &self.options
+    }
+
+    /// The command under review, once one was entered
+    pub fn command(&self) -> Option<&str> {
+        self.command.as_deref()
+    }
+
+    /// Handles one input line, writing any response to `out`
+    pub fn handle_line(&mut self, line: &str, out: &mut impl Write) -> io::Result<Step> {
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        if let Some(continued) = line.strip_suffix('\\') {
+            self.pending.push(continued.trim().to_string());
+            return Ok(Step::Continue);
+        }
+        if !self.pending.is_empty() {
+            self.pending.push(line.trim().to_string());
+            let command = self.pending.join(" ");
+            self.pending.clear();
+            return self.set_command(command, out);
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return Ok(Step::Continue);
+        }
+        let Some(directive) = trimmed.strip_prefix(':') else {
+            return self.set_command(trimmed.to_string(), out);
+        };
+
+        let (name, argument) = match directive.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (directive, ""),
+        };
+        let result = match name {
+            "q" | "quit" => return Ok(Step::Quit),
+            "h" | "help" => {
+                writeln!(out, "{}", HELP)?;
+                return Ok(Step::Continue);
+            }
+            "show" => Ok(()),
+            "w" | "write" => return self.write(argument, out),
+            "version" => argument.parse::<ComposeVersion>().map(|v| self.options.version = v),
+            "env" => argument.parse::<EnvStyle>().map(|v| self.options.env_style = v),
+            "ports" => argument.parse::<PortStyle>().map(|v| self.options.port_style = v),
+            "command" => argument.parse::<CommandStyle>().map(|v| self.options.command_style = v),
+            "format" => argument.parse::<OutputFormat>().map(|v| self.options.output_format = v),
+            _ => Err(format!("Unknown command :{} (try :help)", name)),
+        };
+
+        match result {
+            Ok(()) => self.review(out)?,
+            Err(e) => writeln!(out, "Error: {}", e)?,
+        }
+        Ok(Step::Continue)
+    }
+
+    fn set_command(&mut self, command: String, out: &mut impl Write) -> io::Result<Step> {
+        self.command = Some(command);
+        self.review(out)?;
+        Ok(Step::Continue)
+    }
+
+    /// Writes the flag table, warnings and a preview of the output
+    pub fn review(&self, out: &mut impl Write) -> io::Result<()> {
+        let Some(command) = &self.command else {
+            return writeln!(out, "No command yet; paste a docker run command");
+        };
+
+        let stack = match explain_stack(command, &self.options) {
+            Ok(stack) => stack,
+            Err(e) => return writeln!(out, "Error: {}", e),
+        };
+
+        for explanation in &stack.commands {
+            writeln!(out, "Image: {}", explanation.image)?;
+            if !explanation.command.is_empty() {
+                writeln!(out, "Command: {}", explanation.command.join(" "))?;
+            }
+            writeln!(out, "Flags:")?;
+            for entry in &explanation.entries {
+                writeln!(out, "  {} {} -> {} ({:?})", entry.flag, entry.value, entry.path, entry.arg_type)?;
+            }
+            for flag in &explanation.ignored {
+                writeln!(out, "  {} -> ignored", flag)?;
+            }
+            for flag in &explanation.unmapped {
+                writeln!(out, "  {} -> unmapped", flag)?;
+            }
+        }
+        if !stack.dependencies.is_empty() {
+            writeln!(out, "Dependencies:")?;
+            for dependency in &stack.dependencies {
+                writeln!(
+                    out,
+                    "  {} -> {}: {}",
+                    dependency.service,
+                    dependency.target,
+                    dependency.reasons.join("; ")
+                )?;
+            }
+        }
+
+        writeln!(
+            out,
+            "Settings: version {:?}, env {:?}, ports {:?}, command {:?}, format {:?}",
+            self.options.version,
+            self.options.env_style,
+            self.options.port_style,
+            self.options.command_style,
+            self.options.output_format
+        )?;
+
+        match Composerizer::new(self.options.clone()).convert(command) {
+            Ok(conversion) => {
+                if !conversion.warnings.is_empty() {
+                    writeln!(out, "Warnings:")?;
+                    for warning in &conversion.warnings {
+                        writeln!(out, "  {}", warning)?;
+                    }
+                }
+                writeln!(out, "Preview:")?;
+                writeln!(out, "{}", conversion.output.trim_end())
+            }
+            Err(e) => writeln!(out, "Error: {}", e),
+        }
+    }
+
+    fn write(&self, path: &str, out: &mut impl Write) -> io::Result<Step> {
+        let Some(command) = &self.command else {
+            writeln!(out, "No command yet; paste a docker run command")?;
+            return Ok(Step::Continue);
+        };
+
+        let path = match (path, self.options.output_format) {
+            ("", OutputFormat::Json) => "docker-compose.json",
+            ("", OutputFormat::Yaml) => "docker-compose.yml",
+            (path, _) => path,
+        };
+        let written = Composerizer::new(self.options.clone())
+            .convert(command)
+            .and_then(|conversion| {
+                fs::write(path, conversion.output).map_err(|e| format!("Failed to write {}: {}", path, e))
+            });
+        match written {
+            Ok(()) => {
+                writeln!(out, "Written to {}", path)?;
+                Ok(Step::Quit)
+            }
+            Err(e) => {
+                writeln!(out, "Error: {}", e)?;
+                Ok(Step::Continue)
+            }
+        }
+    }
+}
+
+/// Runs a session until `:quit`, `:write` or the end of `input`
+pub fn run(options: ComposerizeOptions, input: impl BufRead, mut out: impl Write) -> io::Result<Session> {
+    let mut session = Session::new(options);
+    writeln!(out, "{}", HELP)?;
+    for line in input.lines() {
+        if session.handle_line(&line?, &mut out)? == Step::Quit {
+            break;
+        }
+    }
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(lines: &[&str]) -> (Session, String) {
+        let input = lines.join("\n");
+        let mut out = Vec::new();
+        let session = run(ComposerizeOptions::new(), input.as_bytes(), &mut out).unwrap();
+        (session, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_review_shows_mappings_and_preview() {
+        let (_, out) = transcript(&["docker run --rm -p 80:80 --frobnicate nginx"]);
+        assert!(out.contains("Image: nginx"));
+        assert!(out.contains("  -p 80:80 -> ports (Array)"));
+        assert!(out.contains("  --rm -> ignored"));
+        assert!(out.contains("  --frobnicate -> unmapped"));
+        assert!(out.contains("Warnings:\n  Unknown flag --frobnicate treated as a switch"));
+        assert!(out.contains("Preview:\nservices:\n  nginx:"));
+    }
+
+    #[test]
+    fn test_review_of_a_stack() {
+        let (_, out) = transcript(&["docker run --name db postgres && docker run --link db -p 80:80 nginx"]);
+        assert!(out.contains("Image: postgres"));
+        assert!(out.contains("Image: nginx"));
+        assert!(out.contains("  --link db -> links (Array)"), "{}", out);
+        assert!(out.contains("Dependencies:\n  nginx -> db: --link db"));
+        assert!(out.contains("Preview:\nservices:\n  db:"));
+    }
+
+    #[test]
+    fn test_ports_toggle_starts_from_config() {
+        let options = crate::config::Config::parse("port-style = \"long\"").unwrap().to_options().unwrap();
+        let mut out = Vec::new();
+        run(options, "docker run -p 8080:80 nginx\n:ports short".as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        // The review after `:ports short` starts at the last settings line
+        let (long, short) = out.split_at(out.rfind("Settings").unwrap());
+        assert!(long.contains("ports Long") && long.contains("- target: 80"));
+        assert!(short.contains("ports Short") && short.contains("- 8080:80"));
+    }
+
+    #[test]
+    fn test_toggles_update_preview() {
+        let (session, out) = transcript(&[
+            "docker run -e A=1 \\",
+            "  -p 8080:80 nginx",
+            ":version v3x",
+            ":env map",
+            ":ports long",
+            ":env sideways",
+        ]);
+        assert_eq!(session.command(), Some("docker run -e A=1 -p 8080:80 nginx"));
+        assert_eq!(session.options().version, ComposeVersion::V3);
+        assert!(out.contains("version: '3'"));
+        assert!(out.contains("environment:\n      A: '1'"));
+        assert!(out.contains("- target: 80\n      published: '8080'"));
+        assert!(out.contains("Error: Unknown env style: sideways"));
+    }
+
+    #[test]
+    fn test_write_and_quit() {
+        let dir = std::env::temp_dir().join(format!("composerize-interactive-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("compose.json");
+
+        let write = format!(":write {}", path.display());
+        let (_, out) = transcript(&[":write", "docker run redis", ":format json", &write, ":version v9"]);
+        assert!(out.contains("No command yet"));
+        assert!(!out.contains("v9"));
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.contains("\"image\": \"redis\""));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let (session, out) = transcript(&[":quit", "docker run redis"]);
+        assert_eq!(session.command(), None);
+        assert!(!out.contains("Preview"));
+    }
+}
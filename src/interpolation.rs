@@ -0,0 +1,228 @@
+//! Rendering of shell substitutions into compose interpolation syntax.
+//!
+//! Compose interpolates `$VAR`/`${VAR}` itself, so variables found in the
+//! command are emitted as `${VAR}` and every literal `$` is escaped as `$$`.
+//! Substitutions compose cannot express are kept literally with a warning.
+
+use crate::shell::{Segment, Word};
+use indexmap::IndexMap;
+
+/// Controls how substitutions in a command are rendered.
+#[derive(Debug, Clone, Default)]
+pub struct InterpolationOptions {
+    /// Expand variables from this map instead of emitting `${VAR}`
+    pub env: Option<IndexMap<String, String>>,
+}
+
+/// Renders a word into a compose value, appending a warning for every
+/// substitution that cannot be represented.
+pub fn render_word(word: &Word, options: &InterpolationOptions, warnings: &mut Vec<String>) -> String {
+    let mut result = String::new();
+    for segment in &word.segments {
+        match segment {
+            Segment::Literal(text) => result.push_str(&escape(text)),
+            Segment::Expansion(raw) => result.push_str(&render_expansion(raw, options, warnings)),
+        }
+    }
+    result
+}
+
+/// Escapes literal dollar signs so compose does not interpolate them.
+pub fn escape(text: &str) -> String {
+    text.replace('$', "$$")
+}
+
+fn render_expansion(raw: &str, options: &InterpolationOptions, warnings: &mut Vec<String>) -> String {
+    if is_pwd_command(raw) {
+        return ".".to_string();
+    }
+
+    if let Some(name) = simple_variable(raw) {
+        return render_variable(&name, options);
+    }
+
+    if let Some(inner) = raw.strip_prefix("${").and_then(|r| r.strip_suffix('}')) {
+        if let Some(rendered) = render_parameter(inner, options, warnings) {
+            return rendered;
+        }
+    }
+
+    warnings.push(format!(
+        "Cannot represent shell substitution '{}' in compose; kept literally",
+        raw
+    ));
+    escape(raw)
+}
+
+/// Returns the variable name for `$NAME`, `${NAME}`, `$env:NAME` and `%NAME%`.
+fn simple_variable(raw: &str) -> Option<String> {
+    let name = if let Some(inner) = raw.strip_prefix("${").and_then(|r| r.strip_suffix('}')) {
+        inner
+    } else if let Some(inner) = raw.strip_prefix('%').and_then(|r| r.strip_suffix('%')) {
+        if inner.eq_ignore_ascii_case("cd") {
+            "PWD"
+        } else {
+            inner
+        }
+    } else if raw.len() > 5 && raw[..5].eq_ignore_ascii_case("$env:") {
+        &raw[5..]
+    } else {
+        raw.strip_prefix('$')?
+    };
+
+    if is_name(name) {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
+
+fn render_variable(name: &str, options: &InterpolationOptions) -> String {
+    if let Some(value) = options.env.as_ref().and_then(|env| env.get(name)) {
+        return escape(value);
+    }
+    if name == "PWD" {
+        "${PWD:-.}".to_string()
+    } else {
+        format!("${{{}}}", name)
+    }
+}
+
+/// Renders `${NAME<op>word}` for the operators compose understands.
+fn render_parameter(
+    inner: &str,
+    options: &InterpolationOptions,
+    warnings: &mut Vec<String>,
+) -> Option<String> {
+    let name_len = inner
+        .find(|c: char| c != '_' && !c.is_ascii_alphanumeric())
+        .unwrap_or(inner.len());
+    let (name, rest) = inner.split_at(name_len);
+    if !is_name(name) {
+        return None;
+    }
+
+    let op = [":-", ":?", ":+", "-", "?", "+"]
+        .into_iter()
+        .find(|op| rest.starts_with(op))?;
+    let word = &rest[op.len()..];
+
+    let env = match &options.env {
+        Some(env) => env,
+        None => return Some(format!("${{{}}}", inner)),
+    };
+
+    let value = env.get(name);
+    let set = match op {
+        ":-" | ":?" | ":+" => value.is_some_and(|v| !v.is_empty()),
+        _ => value.is_some(),
+    };
+
+    let rendered = match (op, set) {
+        ("-" | ":-", true) | ("?" | ":?", true) => escape(value.map(String::as_str).unwrap_or("")),
+        ("-" | ":-", false) => word.to_string(),
+        ("+" | ":+", true) => word.to_string(),
+        ("+" | ":+", false) => String::new(),
+        _ => {
+            warnings.push(format!(
+                "Variable {} is not set in the supplied environment; kept as '${{{}}}'",
+                name, inner
+            ));
+            format!("${{{}}}", inner)
+        }
+    };
+    Some(rendered)
+}
+
+fn is_pwd_command(raw: &str) -> bool {
+    let inner = match raw.strip_prefix("$(").and_then(|r| r.strip_suffix(')')) {
+        Some(inner) => inner.trim(),
+        None => match raw.strip_prefix('`').and_then(|r| r.strip_suffix('`')) {
+            Some(inner) => inner.trim(),
+            None => return false,
+        },
+    };
+    matches!(inner, "pwd" | "pwd -P" | "pwd -L") || inner.eq_ignore_ascii_case("Get-Location")
+}
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::{split_words, ShellDialect};
+
+    fn render(input: &str, options: &InterpolationOptions) -> (Vec<String>, Vec<String>) {
+        let mut warnings = Vec::new();
+        let words = split_words(input, ShellDialect::Posix)
+            .unwrap()
+            .iter()
+            .map(|w| render_word(w, options, &mut warnings))
+            .collect();
+        (words, warnings)
+    }
+
+    #[test]
+    fn test_variables_become_interpolation() {
+        let (words, warnings) = render(
+            r#"$HOME/x ${DATA}:/data "$DB_PASS" ${PWD}/a $(pwd)/b `pwd`/c ${TAG:-1.21}"#,
+            &InterpolationOptions::default(),
+        );
+        assert_eq!(
+            words,
+            vec!["${HOME}/x", "${DATA}:/data", "${DB_PASS}", "${PWD:-.}/a", "./b", "./c", "${TAG:-1.21}"]
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_literal_dollars_are_escaped() {
+        let (words, warnings) = render(r"'echo $HOME' \$X cost$", &InterpolationOptions::default());
+        assert_eq!(words, vec!["echo $$HOME", "$$X", "cost$$"]);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_unrepresentable_substitutions_warn() {
+        let (words, warnings) = render("$(whoami) ${FILE%.txt} $1 `id -u`", &InterpolationOptions::default());
+        assert_eq!(words, vec!["$$(whoami)", "$${FILE%.txt}", "$$1", "`id -u`"]);
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings[0].contains("$(whoami)"));
+    }
+
+    #[test]
+    fn test_expand_from_env() {
+        let mut env = IndexMap::new();
+        env.insert("HOME".to_string(), "/home/me".to_string());
+        env.insert("PASS".to_string(), "a$b".to_string());
+        let options = InterpolationOptions { env: Some(env) };
+
+        let (words, warnings) = render(
+            "$HOME/x $PASS ${MISSING} ${TAG:-latest} ${HOME:+set} ${REQ:?needed}",
+            &options,
+        );
+        assert_eq!(
+            words,
+            vec!["/home/me/x", "a$$b", "${MISSING}", "latest", "set", "${REQ:?needed}"]
+        );
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_other_dialects() {
+        let mut warnings = Vec::new();
+        let options = InterpolationOptions::default();
+        let ps = split_words(r#"-v "$env:USERPROFILE\data:/data" -v ${PWD}:/app"#, ShellDialect::PowerShell).unwrap();
+        let rendered: Vec<String> = ps.iter().map(|w| render_word(w, &options, &mut warnings)).collect();
+        assert_eq!(rendered, vec!["-v", r"${USERPROFILE}\data:/data", "-v", "${PWD:-.}:/app"]);
+
+        let cmd = split_words("-v %CD%:/app -e 100%", ShellDialect::Cmd).unwrap();
+        let rendered: Vec<String> = cmd.iter().map(|w| render_word(w, &options, &mut warnings)).collect();
+        assert_eq!(rendered, vec!["-v", "${PWD:-.}:/app", "-e", "100%"]);
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+//! Recognition of the program invocation in front of the run arguments.
+//!
+//! Handles `sudo` and `env` wrappers, shell variable assignments, alternate
+//! container runtimes and their global options, so that only the arguments
+//! of `run`/`create` reach the flag parser.
+
+use indexmap::IndexMap;
+
+/// Container CLIs whose `run` syntax matches docker's.
+pub const RUNTIMES: &[&str] = &["docker", "podman", "podman-remote", "nerdctl", "finch"];
+
+/// The program invocation that preceded the run arguments
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Invocation {
+    /// Runtime binary name, e.g. `docker` or `nerdctl`
+    pub runtime: Option<String>,
+    /// Subcommand words, e.g. `["container", "run"]`
+    pub subcommand: Vec<String>,
+    /// Global CLI options such as `context` or `host`, including `DOCKER_*` variables
+    pub global_options: IndexMap<String, String>,
+    /// Number of tokens consumed by the invocation
+    pub consumed: usize,
+}
+
+// Global options taking a value, shared by docker, podman and nerdctl/finch
+const VALUED_GLOBALS: &[&str] = &[
+    "config", "context", "c", "host", "H", "log-level", "l", "tlscacert", "tlscert", "tlskey",
+    "url", "connection", "identity", "root", "runroot", "storage-driver", "storage-opt",
+    "cgroup-manager", "events-backend", "tmpdir", "module", "address", "a", "namespace", "n",
+    "snapshotter", "cni-path", "cni-netconfpath", "data-root", "hosts-dir", "host-gateway-ip",
+];
+
+// Global options that bind a value only through `=`, i.e. switches
+const SWITCH_GLOBALS: &[&str] = &[
+    "debug", "D", "tls", "tlsverify", "remote", "r", "syslog", "insecure-registry",
+    "experimental", "debug-full",
+];
+
+// sudo options that take a value
+const SUDO_VALUED: &[&str] = &["u", "g", "h", "C", "p", "r", "t", "U", "D", "T", "R"];
+
+// Environment variables that select the daemon the command talks to
+const CLIENT_VARIABLES: &[(&str, &str)] = &[
+    ("DOCKER_HOST", "host"),
+    ("DOCKER_CONTEXT", "context"),
+    ("DOCKER_CONFIG", "config"),
+    ("CONTAINER_HOST", "url"),
+    ("CONTAINERD_ADDRESS", "address"),
+    ("CONTAINERD_NAMESPACE", "namespace"),
+];
+
+/// Returns true if `tokens` start with a container runtime, possibly behind
+/// `sudo`, `env` or variable assignments
+pub fn starts_with_runtime(tokens: &[String]) -> bool {
+    let mut warnings = Vec::new();
+    matches!(parse_invocation(tokens, &mut warnings), Ok(invocation) if invocation.runtime.is_some())
+}
+
+/// Recognizes the invocation at the start of `tokens`.
+///
+/// Input without a recognizable runtime is treated as bare run arguments.
+/// Warnings are appended for settings that cannot be carried into compose.
+pub fn parse_invocation(tokens: &[String], warnings: &mut Vec<String>) -> Result<Invocation, String> {
+    let mut invocation = Invocation::default();
+    let mut i = 0;
+
+    loop {
+        i += skip_assignments(&tokens[i..], &mut invocation, warnings);
+        match tokens.get(i).map(|t| program_name(t)) {
+            Some("sudo") => i += 1 + skip_sudo_options(&tokens[i + 1..]),
+            Some("env") => i += 1 + skip_env_options(&tokens[i + 1..]),
+            _ => break,
+        }
+    }
+
+    let runtime = match tokens.get(i).map(|t| program_name(t)) {
+        Some(name) if RUNTIMES.contains(&name) => name.to_string(),
+        _ => {
+            if i > 0 {
+                return Err(format!(
+                    "Expected a container runtime ({}) after '{}'",
+                    RUNTIMES.join(", "),
+                    tokens[..i].join(" ")
+                ));
+            }
+            return Ok(invocation);
+        }
+    };
+    i += 1;
+
+    while let Some(token) = tokens.get(i) {
+        let flag = match token.strip_prefix("--").or_else(|| token.strip_prefix('-')) {
+            Some(flag) if !flag.is_empty() => flag,
+            _ => break,
+        };
+        if let Some((name, value)) = flag.split_once('=') {
+            invocation.global_options.insert(long_global_name(name).to_string(), value.to_string());
+            i += 1;
+        } else if VALUED_GLOBALS.contains(&flag) {
+            let value = tokens
+                .get(i + 1)
+                .ok_or_else(|| format!("Global option '{}' requires a value", token))?;
+            invocation.global_options.insert(long_global_name(flag).to_string(), value.clone());
+            i += 2;
+        } else if SWITCH_GLOBALS.contains(&flag) {
+            invocation.global_options.insert(long_global_name(flag).to_string(), "true".to_string());
+            i += 1;
+        } else {
+            return Err(format!("Unknown global option '{}' for {}", token, runtime));
+        }
+    }
+
+    let words: Vec<&str> = tokens[i..].iter().map(|t| t.as_str()).collect();
+    let subcommand_len = match words.as_slice() {
+        ["container", "run" | "create", ..] | ["network" | "volume", "create", ..] => 2,
+        ["run" | "create", ..] => 1,
+        ["service", ..] => {
+            return Err(format!(
+                "Unsupported command '{} service': swarm services are not supported, only containers can be converted",
+                runtime
+            ))
+        }
+        [] => return Err(format!("Missing subcommand after '{}'", runtime)),
+        [other, ..] => {
+            return Err(format!(
+                "Unsupported command '{} {}': only run, create and network or volume create can be converted",
+                runtime, other
+            ))
+        }
+    };
+    invocation.subcommand = tokens[i..i + subcommand_len].to_vec();
+    invocation.consumed = i + subcommand_len;
+
+    for (name, value) in &invocation.global_options {
+        warnings.push(format!(
+            "Global option --{}={} is not part of the compose file; pass it to `{} compose` instead",
+            name, value, runtime
+        ));
+    }
+    invocation.runtime = Some(runtime);
+
+    Ok(invocation)
+}
+
+/// Returns the program name of a token, without directory or `.exe` suffix
+fn program_name(token: &str) -> &str {
+    let name = token.rsplit(['/', '\\']).next().unwrap_or(token);
+    name.strip_suffix(".exe").unwrap_or(name)
+}
+
+fn long_global_name(flag: &str) -> &str {
+    match flag {
+        "c" => "context",
+        "H" => "host",
+        "l" => "log-level",
+        "D" => "debug",
+        "r" => "remote",
+        "a" => "address",
+        "n" => "namespace",
+        other => other,
+    }
+}
+
+fn is_assignment(token: &str) -> bool {
+    match token.split_once('=') {
+        Some((name, _)) => {
+            let mut chars = name.chars();
+            matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+                && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        }
+        None => false,
+    }
+}
+
+/// Skips `NAME=value` prefixes, recording the ones that configure the client
+fn skip_assignments(tokens: &[String], invocation: &mut Invocation, warnings: &mut Vec<String>) -> usize {
+    let mut count = 0;
+    for token in tokens {
+        if !is_assignment(token) {
+            break;
+        }
+        if let Some((name, value)) = token.split_once('=') {
+            match CLIENT_VARIABLES.iter().find(|(var, _)| *var == name) {
+                Some((_, option)) => {
+                    invocation.global_options.insert(option.to_string(), value.to_string());
+                }
+                None => warnings.push(format!(
+                    "Ignoring environment assignment '{}' in front of the command",
+                    token
+                )),
+            }
+        }
+        count += 1;
+    }
+    count
+}
+
+fn skip_sudo_options(tokens: &[String]) -> usize {
+    let mut i = 0;
+    while let Some(token) = tokens.get(i) {
+        if token == "--" {
+            return i + 1;
+        }
+        if token.starts_with("--") {
+            // Long sudo options take their value with `=`
+            i += 1;
+        } else if let Some(flags) = token.strip_prefix('-').filter(|f| !f.is_empty()) {
+            // The value of the last valued option in a cluster may be attached or follow
+            let valued = flags.chars().position(|c| SUDO_VALUED.contains(&c.to_string().as_str()));
+            i += match valued {
+                Some(pos) if pos + 1 == flags.len() => 2,
+                _ => 1,
+            };
+        } else {
+            break;
+        }
+    }
+    i
+}
+
+fn skip_env_options(tokens: &[String]) -> usize {
+    let mut i = 0;
+    while let Some(token) = tokens.get(i) {
+        match token.as_str() {
+            "--" => return i + 1,
+            "-u" | "-C" | "--unset" | "--chdir" => i += 2,
+            t if t.starts_with('-') && t.len() > 1 => i += 1,
+            _ => break,
+        }
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> (Result<Invocation, String>, Vec<String>) {
+        let tokens = crate::shell::tokenize(input).unwrap();
+        let mut warnings = Vec::new();
+        (parse_invocation(&tokens, &mut warnings), warnings)
+    }
+
+    #[test]
+    fn test_plain_docker_run() {
+        let (invocation, warnings) = parse("docker run nginx");
+        let invocation = invocation.unwrap();
+        assert_eq!(invocation.runtime.as_deref(), Some("docker"));
+        assert_eq!(invocation.subcommand, vec!["run"]);
+        assert_eq!(invocation.consumed, 2);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_bare_arguments() {
+        let (invocation, _) = parse("-p 80:80 nginx");
+        assert_eq!(invocation.unwrap(), Invocation::default());
+    }
+
+    #[test]
+    fn test_global_options() {
+        let (invocation, warnings) = parse("docker --context prod -H tcp://x:2376 --tlsverify run nginx");
+        let invocation = invocation.unwrap();
+        assert_eq!(invocation.global_options["context"], "prod");
+        assert_eq!(invocation.global_options["host"], "tcp://x:2376");
+        assert_eq!(invocation.global_options["tlsverify"], "true");
+        assert_eq!(invocation.consumed, 7);
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].contains("--context=prod"));
+    }
+
+    #[test]
+    fn test_wrappers_and_runtimes() {
+        let cases = [
+            ("sudo docker run nginx", "docker", 3),
+            ("sudo -E -u root docker run nginx", "docker", 6),
+            ("env DOCKER_HOST=ssh://h docker run nginx", "docker", 4),
+            ("/usr/local/bin/nerdctl --namespace k8s.io run nginx", "nerdctl", 4),
+            ("finch container create nginx", "finch", 3),
+            ("podman-remote --connection box run nginx", "podman-remote", 4),
+            ("docker.exe run nginx", "docker", 2),
+        ];
+        for (input, runtime, consumed) in cases {
+            let (invocation, _) = parse(input);
+            let invocation = invocation.unwrap();
+            assert_eq!(invocation.runtime.as_deref(), Some(runtime), "input: {}", input);
+            assert_eq!(invocation.consumed, consumed, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_client_variables_recorded() {
+        let (invocation, warnings) = parse("DOCKER_CONTEXT=prod FOO=1 docker run nginx");
+        let invocation = invocation.unwrap();
+        assert_eq!(invocation.global_options["context"], "prod");
+        assert!(warnings.iter().any(|w| w.contains("FOO=1")));
+    }
+
+    #[test]
+    fn test_unsupported_subcommand() {
+        let (invocation, _) = parse("docker ps -a");
+        assert!(invocation.unwrap_err().contains("docker ps"));
+        let (invocation, _) = parse("sudo ls");
+        assert!(invocation.is_err());
+        let (invocation, _) = parse("docker service create --replicas 3 nginx");
+        assert!(invocation.unwrap_err().contains("swarm services are not supported"));
+    }
+}
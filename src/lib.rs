@@ -1,277 +1,529 @@
+pub mod image;
+pub mod interpolation;
+pub mod interactive;
+pub mod invocation;
+pub mod config;
+pub mod explain;
+pub mod gpu;
 pub mod mappings;
+pub mod naming;
+pub mod network;
+pub mod options;
 pub mod parser;
+pub mod serializer;
+pub mod shell;
+pub mod stack;
+pub mod units;
+pub mod volume;
 
 use indexmap::IndexMap;
+use naming::NamingPolicy;
+use options::{ComposerizeOptions, EnvStyle, OutputFormat, PortStyle, ResourcePolicy, Strictness};
+use parser::{CommandStyle, StackCommand};
 use serde_yaml::Value;
 use std::fs;
 use std::path::Path;
 
-pub fn composerize(
-    input: &str,
-    _existing_compose: &str,
-    format: &str,
-    _indent: usize,
-) -> Result<String, String> {
-    let (image, command, args) = parser::parse_docker_command(input)?;
-    
-    let network = args.get("network")
-        .or_else(|| args.get("net"))
-        .and_then(|v| v.first())
-        .map(|s| s.as_str())
-        .unwrap_or("default");
-    
-    let mut service_value = parser::build_compose_value(&args, network)?;
-    
-    // Add image
-    if let Value::Mapping(ref mut map) = service_value {
-        map.insert(
-            Value::String("image".to_string()),
-            Value::String(image.clone())
-        );
-        
-        // Add command if present
-        if !command.is_empty() {
+/// Output of a conversion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// Serialized compose file
+    pub output: String,
+    /// Problems that did not prevent conversion
+    pub warnings: Vec<String>,
+    /// `depends_on` edges added between the services of a stack
+    pub dependencies: Vec<stack::Dependency>,
+    /// Bind mount sources made relative by [`ComposerizeOptions::bind_rewrite`]
+    pub path_rewrites: Vec<volume::PathRewrite>,
+}
+
+/// Converts docker commands to compose files with a fixed set of options
+#[derive(Debug, Clone, Default)]
+pub struct Composerizer {
+    options: ComposerizeOptions,
+}
+
+impl Composerizer {
+    pub fn new(options: ComposerizeOptions) -> Self {
+        Self { options }
+    }
+
+    pub fn options(&self) -> &ComposerizeOptions {
+        &self.options
+    }
+
+    /// Converts a docker run command into a compose file
+    pub fn convert(&self, input: &str) -> Result<Conversion, String> {
+        let mut warnings = Vec::new();
+        let (compose, dependencies, path_rewrites) = self.build(input, &mut warnings)?;
+
+        if self.options.strictness == Strictness::Strict && !warnings.is_empty() {
+            return Err(format!("Conversion failed in strict mode: {}", warnings.join("; ")));
+        }
+
+        let output = serializer::serializer_for(self.options.output_format, self.options.indent)
+            .serialize(&compose)?;
+        Ok(Conversion {
+            output,
+            warnings,
+            dependencies,
+            path_rewrites,
+        })
+    }
+
+    fn build(
+        &self,
+        input: &str,
+        warnings: &mut Vec<String>,
+    ) -> Result<(Value, Vec<stack::Dependency>, Vec<volume::PathRewrite>), String> {
+        let mut commands = Vec::new();
+        let mut created = CreatedResources::default();
+        for command in parser::parse_stack(input, &self.options.parse_options())? {
+            match command {
+                StackCommand::Run(parsed) => commands.push(parsed),
+                StackCommand::CreateNetwork(network) => {
+                    warnings.extend(network.warnings);
+                    created.networks.insert(network.name, network.definition);
+                }
+                StackCommand::CreateVolume(volume) => {
+                    warnings.extend(volume.warnings);
+                    created.volumes.insert(volume.name, volume.definition);
+                }
+            }
+        }
+        if commands.is_empty() {
+            return Err("No docker run command to convert".to_string());
+        }
+
+        let mut services = serde_yaml::Mapping::new();
+        let mut containers = stack::ContainerNames::new();
+        for parsed in &commands {
+            warnings.extend(parsed.warnings.iter().cloned());
+
+            let image = image::normalize_image(&parsed.image, &self.options.image, warnings);
+            let service_name = service_name_for(&parsed.image, &parsed.args, &self.options.naming);
+            let service_name = unique_service_name(&services, &service_name);
+            if let Some(container) = parsed.args.get("name").and_then(|names| names.last()) {
+                containers.insert(container.clone(), service_name.clone());
+            }
+            let service = self.service_value(&image, &parsed.command, &parsed.args)?;
+            services.insert(Value::String(service_name), service);
+        }
+
+        let mut dependencies = Vec::new();
+        if commands.len() > 1 {
+            dependencies = stack::resolve_references(&mut services, &containers, warnings);
+            if self.options.infer_depends_on {
+                dependencies.extend(stack::infer_dependencies(&services));
+            }
+            dependencies = stack::add_depends_on(&mut services, dependencies, warnings);
+        }
+        let path_rewrites = match &self.options.bind_rewrite {
+            Some(rewrite) => volume::rewrite_binds(&mut services, rewrite),
+            None => Vec::new(),
+        };
+        let args: Vec<&parser::ParsedArgs> = commands.iter().map(|parsed| &parsed.args).collect();
+        let compose = compose_document(&self.options, services, &args, &created);
+
+        let compose = match &self.options.merge_target {
+            Some(existing) => merge_compose(existing, compose, warnings)?,
+            None => compose,
+        };
+        Ok((compose, dependencies, path_rewrites))
+    }
+
+    /// Builds the service definition, applying the output style options
+    fn service_value(&self, image: &str, command: &[String], args: &parser::ParsedArgs) -> Result<Value, String> {
+        let network = parser::primary_network(args);
+        let mut service_value = parser::build_compose_value_with(args, &network, &self.options.mappings)?;
+
+        if let Value::Mapping(ref mut map) = service_value {
             map.insert(
-                Value::String("command".to_string()),
-                Value::String(command.join(" "))
+                Value::String("image".to_string()),
+                Value::String(image.to_string())
             );
+
+            if !command.is_empty() {
+                map.insert(
+                    Value::String("command".to_string()),
+                    parser::command_value(command, self.options.command_style)
+                );
+            }
+
+            if self.options.command_style != CommandStyle::Exec {
+                if let Some(entrypoint) = map.get_mut("entrypoint") {
+                    restyle_command(entrypoint, self.options.command_style);
+                }
+            }
+
+            if !self.options.secret_patterns.is_empty() {
+                if let Some(environment) = map.get_mut("environment") {
+                    redact_secrets(environment, &self.options.secret_patterns);
+                }
+            }
+
+            if self.options.env_style == EnvStyle::Map {
+                if let Some(environment) = map.get_mut("environment") {
+                    *environment = env_map(environment);
+                }
+            }
+
+            if self.options.port_style == PortStyle::Long {
+                if let Some(Value::Sequence(ports)) = map.get_mut("ports") {
+                    for port in ports.iter_mut() {
+                        if let Some(long) = port.as_str().and_then(long_port) {
+                            *port = long;
+                        }
+                    }
+                }
+            }
         }
+
+        Ok(service_value)
     }
+}
+
+pub fn composerize(
+    input: &str,
+    existing_compose: &str,
+    format: &str,
+    indent: usize,
+) -> Result<String, String> {
+    composerize_with_naming(input, existing_compose, format, indent, &NamingPolicy::default())
+}
+
+/// Converts docker run command to YAML, choosing the service name by `naming`
+pub fn composerize_with_naming(
+    input: &str,
+    existing_compose: &str,
+    format: &str,
+    indent: usize,
+    naming: &NamingPolicy,
+) -> Result<String, String> {
+    let options = legacy_options(existing_compose, format, indent, naming)?
+        .with_output_format(OutputFormat::Yaml);
+    Composerizer::new(options).convert(input).map(|conversion| conversion.output)
+}
+
+/// Converts docker run command to JSON
+pub fn composerize_to_json(
+    input: &str,
+    existing_compose: &str,
+    format: &str,
+    indent: usize,
+) -> Result<String, String> {
+    composerize_to_json_with_naming(input, existing_compose, format, indent, &NamingPolicy::default())
+}
+
+/// Converts docker run command to JSON, choosing the service name by `naming`
+pub fn composerize_to_json_with_naming(
+    input: &str,
+    existing_compose: &str,
+    format: &str,
+    indent: usize,
+    naming: &NamingPolicy,
+) -> Result<String, String> {
+    let options = legacy_options(existing_compose, format, indent, naming)?
+        .with_output_format(OutputFormat::Json);
+    Composerizer::new(options).convert(input).map(|conversion| conversion.output)
+}
+
+/// Maps the positional parameters of the string-based functions to options
+fn legacy_options(
+    existing_compose: &str,
+    format: &str,
+    indent: usize,
+    naming: &NamingPolicy,
+) -> Result<ComposerizeOptions, String> {
+    Ok(ComposerizeOptions::new()
+        .with_version(format.parse()?)
+        .with_indent(indent)
+        .with_naming(naming.clone())
+        .with_merge_target(existing_compose))
+}
+
+/// Networks and volumes defined by resource commands of a script
+#[derive(Debug, Default)]
+struct CreatedResources {
+    networks: IndexMap<String, serde_yaml::Mapping>,
+    volumes: IndexMap<String, serde_yaml::Mapping>,
+}
+
+/// Assembles the top-level compose document around the services
+fn compose_document(
+    options: &ComposerizeOptions,
+    services: serde_yaml::Mapping,
+    args: &[&parser::ParsedArgs],
+    created: &CreatedResources,
+) -> Value {
+    let mut compose = serde_yaml::Mapping::new();
     
-    let service_name = get_service_name(&image);
-    
-    let mut services = IndexMap::new();
-    services.insert(service_name, service_value);
-    
-    let version = match format {
-        "v2x" => Some("2".to_string()),
-        "v3x" => Some("3".to_string()),
-        "latest" => None,
-        _ => return Err(format!("Unknown format: {}", format)),
-    };
-    
-    let mut compose = IndexMap::new();
-    
-    if let Some(v) = version {
+    if let Some(v) = options.version.version_key() {
         compose.insert(
             Value::String("version".to_string()),
-            Value::String(v)
+            Value::String(v.to_string())
         );
     }
     
-    compose.insert(
-        Value::String("services".to_string()),
-        Value::Mapping(services.into_iter()
-            .map(|(k, v)| (Value::String(k), v))
-            .collect())
-    );
+    compose.insert(Value::String("services".to_string()), Value::Mapping(services));
     
     // Collect used networks and volumes
-    let (networks, volumes) = collect_resources(&args);
+    let (networks, volumes) = collect_resources(args);
     
-    // Add networks section if present
-    if !networks.is_empty() {
-        let mut networks_map = IndexMap::new();
-        for net in networks {
-            if net != "default" && net != "bridge" && net != "host" && net != "none" {
-                let mut net_config = IndexMap::new();
-                net_config.insert(
-                    Value::String("external".to_string()),
-                    Value::Bool(true)
-                );
-                networks_map.insert(net.to_string(), Value::Mapping(net_config.into_iter().collect()));
-            }
-        }
-        if !networks_map.is_empty() {
-            compose.insert(
-                Value::String("networks".to_string()),
-                Value::Mapping(networks_map.into_iter()
-                    .map(|(k, v)| (Value::String(k), v))
-                    .collect())
-            );
+    // Add networks section if present, including created networks no
+    // service uses
+    let mut networks_map = serde_yaml::Mapping::new();
+    for net in networks.into_iter().chain(created.networks.keys().cloned()) {
+        if net != "default" && !parser::is_network_mode(&net) && !networks_map.contains_key(net.as_str()) {
+            let declaration = resource_declaration(options.network_policy, created.networks.get(&net), true);
+            networks_map.insert(Value::String(net), declaration);
         }
     }
+    if !networks_map.is_empty() {
+        compose.insert(Value::String("networks".to_string()), Value::Mapping(networks_map));
+    }
     
-    // Add volumes section if there are named volumes
-    if !volumes.is_empty() {
-        let mut volumes_map = IndexMap::new();
-        for vol in volumes {
-            volumes_map.insert(vol.to_string(), Value::Null);
+    // Add volumes section if there are named or created volumes
+    let mut volumes_map = serde_yaml::Mapping::new();
+    for vol in volumes.into_iter().chain(created.volumes.keys().cloned()) {
+        if !volumes_map.contains_key(vol.as_str()) {
+            let declaration = resource_declaration(options.volume_policy, created.volumes.get(&vol), false);
+            volumes_map.insert(Value::String(vol), declaration);
         }
-        compose.insert(
-            Value::String("volumes".to_string()),
-            Value::Mapping(volumes_map.into_iter()
-                .map(|(k, v)| (Value::String(k), v))
-                .collect())
-        );
+    }
+    if !volumes_map.is_empty() {
+        compose.insert(Value::String("volumes".to_string()), Value::Mapping(volumes_map));
     }
     
-    let compose_value = Value::Mapping(compose.into_iter().collect());
-    
-    serde_yaml::to_string(&compose_value)
-        .map_err(|e| format!("Failed to serialize: {}", e))
+    Value::Mapping(compose)
 }
 
-/// Collects used networks and named volumes from arguments
-fn collect_resources(args: &IndexMap<String, Vec<String>>) -> (Vec<String>, Vec<String>) {
-    let mut networks = Vec::new();
-    let mut volumes = Vec::new();
-    
-    // Collect networks
-    if let Some(nets) = args.get("network").or_else(|| args.get("net")) {
-        for net in nets {
-            if !networks.contains(net) {
-                networks.push(net.clone());
-            }
-        }
+/// Top-level entry of a network or volume under `policy`, given its
+/// definition from a create command of the script. Under `auto`, resources
+/// the script does not create are external if `external_by_default`.
+fn resource_declaration(policy: ResourcePolicy, created: Option<&serde_yaml::Mapping>, external_by_default: bool) -> Value {
+    let external = match (policy, created) {
+        (ResourcePolicy::External, _) => true,
+        (ResourcePolicy::Auto, None) => external_by_default,
+        _ => false,
+    };
+    if external {
+        let mut config = serde_yaml::Mapping::new();
+        config.insert(Value::String("external".to_string()), Value::Bool(true));
+        return Value::Mapping(config);
     }
-    
-    // Collect named volumes (not bind mounts)
-    if let Some(vols) = args.get("volume").or_else(|| args.get("v")) {
-        for vol in vols {
-            // Named volume if it doesn't start with / or . or ~
-            if !vol.starts_with('/') && !vol.starts_with('.') && !vol.starts_with('~') {
-                if let Some(vol_name) = vol.split(':').next() {
-                    if !volumes.contains(&vol_name.to_string()) {
-                        volumes.push(vol_name.to_string());
-                    }
-                }
+    created.map_or(Value::Null, |definition| Value::Mapping(definition.clone()))
+}
+
+/// Adds the service, networks and volumes of `compose` to an existing compose
+/// file. Existing entries win; a clashing service name gets a numeric suffix.
+fn merge_compose(existing: &str, compose: Value, warnings: &mut Vec<String>) -> Result<Value, String> {
+    // JSON is valid YAML, so one parser handles both
+    let mut target: Value = serde_yaml::from_str(existing)
+        .map_err(|e| format!("Failed to parse existing compose file: {}", e))?;
+    let target_map = target
+        .as_mapping_mut()
+        .ok_or("Existing compose file must be a mapping")?;
+    let Value::Mapping(compose) = compose else {
+        return Ok(target);
+    };
+
+    for (section, entries) in compose {
+        let Value::Mapping(entries) = entries else {
+            // The existing file keeps its own version
+            continue;
+        };
+
+        let target_section = target_map
+            .entry(section.clone())
+            .or_insert_with(|| Value::Mapping(serde_yaml::Mapping::new()));
+        if target_section.is_null() {
+            *target_section = Value::Mapping(serde_yaml::Mapping::new());
+        }
+        let target_section = target_section
+            .as_mapping_mut()
+            .ok_or_else(|| format!("'{}' in existing compose file must be a mapping", section.as_str().unwrap_or("")))?;
+
+        for (key, value) in entries {
+            if !target_section.contains_key(&key) {
+                target_section.insert(key, value);
+            } else if section.as_str() == Some("services") {
+                let name = key.as_str().unwrap_or_default();
+                let unique = unique_service_name(target_section, name);
+                warnings.push(format!("Service '{}' already exists; added as '{}'", name, unique));
+                target_section.insert(Value::String(unique), value);
             }
         }
     }
-    
-    (networks, volumes)
-}
 
-pub fn get_service_name(image: &str) -> String {
-    let name = if image.contains('/') {
-        image.split('/').last().unwrap_or(image)
-    } else {
-        image
-    };
-    
-    let name = if name.contains(':') {
-        name.split(':').next().unwrap_or(name)
-    } else {
-        name
-    };
-    
-    name.to_string()
+    Ok(target)
 }
 
-/// Converts docker run command to JSON
-pub fn composerize_to_json(
-    input: &str,
-    _existing_compose: &str,
-    format: &str,
-    indent: usize,
-) -> Result<String, String> {
-    let (image, command, args) = parser::parse_docker_command(input)?;
+/// Returns `name`, or `name-2`, `name-3`... if `services` already has it
+fn unique_service_name(services: &serde_yaml::Mapping, name: &str) -> String {
+    if !services.contains_key(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}-{}", name, n))
+        .find(|candidate| !services.contains_key(candidate.as_str()))
+        .unwrap_or_default()
+}
 
-    let network = args
-        .get("network")
-        .or_else(|| args.get("net"))
-        .and_then(|v| v.first())
-        .map(|s| s.as_str())
-        .unwrap_or("default");
+/// Re-renders an exec-form `entrypoint` sequence in the given style
+fn restyle_command(value: &mut Value, style: CommandStyle) {
+    if let Value::Sequence(items) = value {
+        let args: Option<Vec<String>> = items.iter().map(|v| v.as_str().map(String::from)).collect();
+        if let Some(args) = args {
+            *value = parser::command_value(&args, style);
+        }
+    }
+}
 
-    let mut service_value = parser::build_compose_value(&args, network)?;
+/// Replaces the values of `KEY=value` entries whose key matches one of
+/// `patterns` with `${KEY}`, leaving interpolations alone
+fn redact_secrets(environment: &mut Value, patterns: &[String]) {
+    let Value::Sequence(items) = environment else {
+        return;
+    };
 
-    if let Value::Mapping(ref mut map) = service_value {
-        map.insert(
-            Value::String("image".to_string()),
-            Value::String(image.clone()),
-        );
+    for item in items.iter_mut() {
+        let redacted = match item.as_str().and_then(|entry| entry.split_once('=')) {
+            Some((key, value))
+                if !value.starts_with("${") && patterns.iter().any(|pattern| wildcard_match(pattern, key)) =>
+            {
+                format!("{}=${{{}}}", key, key)
+            }
+            _ => continue,
+        };
+        *item = Value::String(redacted);
+    }
+}
 
-        if !command.is_empty() {
-            map.insert(
-                Value::String("command".to_string()),
-                Value::String(command.join(" ")),
-            );
+/// Case-insensitive match supporting `*` (any run) and `?` (one character)
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let text: Vec<char> = text.to_ascii_lowercase().chars().collect();
+
+    // matches[j]: pattern[..i] matches text[..j]
+    let mut matches = vec![false; text.len() + 1];
+    matches[0] = true;
+    for p in &pattern {
+        let mut next = vec![false; text.len() + 1];
+        for j in 0..=text.len() {
+            next[j] = match p {
+                '*' => matches[j] || (j > 0 && next[j - 1]),
+                '?' => j > 0 && matches[j - 1],
+                c => j > 0 && matches[j - 1] && text[j - 1] == *c,
+            };
         }
+        matches = next;
     }
+    matches[text.len()]
+}
 
-    let service_name = get_service_name(&image);
-
-    let mut services = IndexMap::new();
-    services.insert(service_name, service_value);
+/// Converts a `[ip:][host:]container[/protocol]` port to the long syntax;
+/// returns None for container port ranges and other specs it cannot express
+fn long_port(spec: &str) -> Option<Value> {
+    let (address, protocol) = match spec.rsplit_once('/') {
+        Some((address, protocol)) => (address, Some(protocol)),
+        None => (spec, None),
+    };
 
-    let version = match format {
-        "v2x" => Some("2".to_string()),
-        "v3x" => Some("3".to_string()),
-        "latest" => None,
-        _ => return Err(format!("Unknown format: {}", format)),
+    let (host_ip, rest) = match address.strip_prefix('[') {
+        // Bracketed IPv6 address
+        Some(bracketed) => {
+            let (ip, rest) = bracketed.split_once("]:")?;
+            (Some(ip), rest)
+        }
+        None => match address.matches(':').count() {
+            2 => {
+                let (ip, rest) = address.split_once(':')?;
+                (Some(ip), rest)
+            }
+            0 | 1 => (None, address),
+            _ => return None,
+        },
     };
+    let (published, target) = match rest.rsplit_once(':') {
+        Some((published, target)) => (Some(published), target),
+        None => (None, rest),
+    };
+    let target: u16 = target.parse().ok()?;
+
+    let mut port = serde_yaml::Mapping::new();
+    port.insert(Value::from("target"), Value::from(target));
+    if let Some(published) = published.filter(|p| !p.is_empty()) {
+        port.insert(Value::from("published"), Value::from(published));
+    }
+    if let Some(host_ip) = host_ip.filter(|ip| !ip.is_empty()) {
+        port.insert(Value::from("host_ip"), Value::from(host_ip));
+    }
+    if let Some(protocol) = protocol {
+        port.insert(Value::from("protocol"), Value::from(protocol));
+    }
+    Some(Value::Mapping(port))
+}
 
-    let mut compose = IndexMap::new();
+/// Turns `KEY=value` entries into a mapping; a bare `KEY` maps to null so
+/// compose takes the value from the shell environment
+fn env_map(environment: &Value) -> Value {
+    let Value::Sequence(items) = environment else {
+        return environment.clone();
+    };
 
-    if let Some(v) = version {
-        compose.insert(Value::String("version".to_string()), Value::String(v));
+    let mut map = serde_yaml::Mapping::new();
+    for item in items.iter().filter_map(|v| v.as_str()) {
+        let (key, value) = match item.split_once('=') {
+            Some((key, value)) => (key, Value::String(value.to_string())),
+            None => (item, Value::Null),
+        };
+        map.insert(Value::String(key.to_string()), value);
     }
+    Value::Mapping(map)
+}
 
-    compose.insert(
-        Value::String("services".to_string()),
-        Value::Mapping(
-            services
-                .into_iter()
-                .map(|(k, v)| (Value::String(k), v))
-                .collect(),
-        ),
-    );
+/// Collects used networks and named volumes from arguments
+fn collect_resources(commands: &[&parser::ParsedArgs]) -> (Vec<String>, Vec<String>) {
+    let mut networks = Vec::new();
+    let mut volumes = Vec::new();
 
-    // Collect used networks and volumes
-    let (networks, volumes) = collect_resources(&args);
-    
-    // Add networks section if present
-    if !networks.is_empty() {
-        let mut networks_map = IndexMap::new();
-        for net in networks {
-            if net != "default" && net != "bridge" && net != "host" && net != "none" {
-                let mut net_config = IndexMap::new();
-                net_config.insert(
-                    Value::String("external".to_string()),
-                    Value::Bool(true)
-                );
-                networks_map.insert(net.to_string(), Value::Mapping(net_config.into_iter().collect()));
+    for args in commands {
+        collect_command_resources(args, &mut networks, &mut volumes);
+    }
+    (networks, volumes)
+}
+
+fn collect_command_resources(args: &parser::ParsedArgs, networks: &mut Vec<String>, volumes: &mut Vec<String>) {
+    // Collect networks
+    for value in parser::network_values(args) {
+        if let Ok(network) = parser::parse_network_spec(value) {
+            if !networks.contains(&network.name) {
+                networks.push(network.name);
             }
         }
-        if !networks_map.is_empty() {
-            compose.insert(
-                Value::String("networks".to_string()),
-                Value::Mapping(networks_map.into_iter()
-                    .map(|(k, v)| (Value::String(k), v))
-                    .collect())
-            );
-        }
     }
     
-    // Add volumes section if there are named volumes
-    if !volumes.is_empty() {
-        let mut volumes_map = IndexMap::new();
-        for vol in volumes {
-            volumes_map.insert(vol.to_string(), Value::Null);
+    // Collect named volumes (not bind mounts or anonymous volumes)
+    let specs = args.get("volume").into_iter().chain(args.get("v")).flatten();
+    for spec in specs.filter_map(|spec| volume::parse_volume_spec(spec).ok()) {
+        if let volume::VolumeSource::Named(name) = spec.source {
+            if !volumes.contains(&name) {
+                volumes.push(name);
+            }
         }
-        compose.insert(
-            Value::String("volumes".to_string()),
-            Value::Mapping(volumes_map.into_iter()
-                .map(|(k, v)| (Value::String(k), v))
-                .collect())
-        );
     }
+}
 
-    let compose_value = Value::Mapping(compose.into_iter().collect());
-
-    // Convert to JSON
-    let json_value: serde_json::Value = serde_yaml::from_value(compose_value)
-        .map_err(|e| format!("Failed to convert to JSON: {}", e))?;
+/// Derives a compose service name from an image reference
+pub fn get_service_name(image: &str) -> String {
+    naming::service_name(image, None, &NamingPolicy::Image)
+}
 
-    if indent > 0 {
-        serde_json::to_string_pretty(&json_value)
-            .map_err(|e| format!("Failed to serialize JSON: {}", e))
-    } else {
-        serde_json::to_string(&json_value)
-            .map_err(|e| format!("Failed to serialize JSON: {}", e))
-    }
+/// Derives the service name from `--name` and the image according to `naming`
+fn service_name_for(image: &str, args: &IndexMap<String, Vec<String>>, naming: &NamingPolicy) -> String {
+    let container_name = args.get("name").and_then(|names| names.last());
+    naming::service_name(image, container_name.map(|n| n.as_str()), naming)
 }
 
 /// Converts YAML to JSON
@@ -335,6 +587,7 @@ pub fn convert_file(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::options::ComposeVersion;
 
     #[test]
     fn test_simple_nginx() {
@@ -363,6 +616,27 @@ mod tests {
         assert!(yaml.contains("NODE_ENV=production"));
     }
 
+    #[test]
+    fn test_env_preserves_quoted_whitespace() {
+        let (_, _, args) = parser::parse_docker_command(r#"docker run -e "A=  b" --name="web"1 nginx"#).unwrap();
+        assert_eq!(args["e"], vec!["A=  b"]);
+        assert_eq!(args["name"], vec!["web1"]);
+    }
+
+    #[test]
+    fn test_global_options_before_run() {
+        for input in [
+            "docker --context prod run -p 80:80 nginx",
+            "sudo docker -H tcp://x:2376 run -p 80:80 nginx",
+            "docker container create -p 80:80 nginx",
+            "nerdctl run -p 80:80 nginx",
+        ] {
+            let yaml = composerize(input, "", "latest", 2).unwrap();
+            assert!(yaml.contains("image: nginx"), "input: {}", input);
+            assert!(yaml.contains("80:80"), "input: {}", input);
+        }
+    }
+
     #[test]
     fn test_with_volumes() {
         let result = composerize("docker run -v /data:/app nginx", "", "latest", 2);
@@ -406,7 +680,26 @@ mod tests {
         let yaml = result.unwrap();
         assert!(yaml.contains("stdin_open: true"));
         assert!(yaml.contains("tty: true"));
-        assert!(yaml.contains("command: bash"));
+        assert!(yaml.contains("command:\n    - bash"));
+    }
+
+    #[test]
+    fn test_command_keeps_quoted_arguments() {
+        let yaml = composerize(r#"docker run alpine sh -c "echo a b""#, "", "latest", 2).unwrap();
+        assert!(yaml.contains("command:\n    - sh\n    - -c\n    - echo a b"));
+    }
+
+    #[test]
+    fn test_backquotes_and_redirections() {
+        let conversion = Composerizer::default()
+            .convert("docker run -v `pwd`:/x nginx > out.log")
+            .unwrap();
+        assert!(conversion.output.contains("- .:/x"));
+        assert!(!conversion.output.contains("command"));
+        assert_eq!(
+            conversion.warnings,
+            vec!["Ignoring shell redirection '> out.log': it applies to the docker client, not the container"]
+        );
     }
 
     #[test]
@@ -504,6 +797,23 @@ mod tests {
         assert_eq!(get_service_name("docker.io/library/nginx:1.21"), "nginx");
     }
 
+    #[test]
+    fn test_get_service_name_with_digest() {
+        assert_eq!(get_service_name("nginx@sha256:0123abcd"), "nginx");
+        assert_eq!(get_service_name("localhost:5000/My.App:2"), "my-app");
+    }
+
+    #[test]
+    fn test_service_name_prefers_container_name() {
+        let yaml = composerize("docker run --name Web.1 nginx", "", "latest", 2).unwrap();
+        assert!(yaml.contains("services:\n  web-1:"));
+        assert!(yaml.contains("container_name: Web.1"));
+
+        let naming = NamingPolicy::Fixed("frontend".to_string());
+        let yaml = composerize_with_naming("docker run --name web nginx", "", "latest", 2, &naming).unwrap();
+        assert!(yaml.contains("services:\n  frontend:"));
+    }
+
     #[test]
     fn test_healthcheck() {
         let result = composerize(
@@ -519,6 +829,35 @@ mod tests {
         assert!(yaml.contains("interval: 30s"));
     }
 
+    #[test]
+    fn test_healthcheck_forms() {
+        let healthcheck = |input: &str| {
+            let yaml = composerize(input, "", "latest", 2).unwrap();
+            let compose: Value = serde_yaml::from_str(&yaml).unwrap();
+            compose["services"]["nginx"]["healthcheck"].clone()
+        };
+        let yaml = |s: &str| serde_yaml::from_str::<Value>(s).unwrap();
+
+        assert_eq!(
+            healthcheck(r#"docker run --health-cmd '["CMD","curl","-f","http://x"]' --health-interval 90s --health-start-interval 500ms nginx"#),
+            yaml("{test: [CMD, curl, -f, 'http://x'], interval: 1m30s, start_interval: 500ms}")
+        );
+        assert_eq!(
+            healthcheck(r#"docker run --health-cmd '["pg_isready"]' --health-timeout=1.5s nginx"#),
+            yaml("{test: [CMD, pg_isready], timeout: 1s500ms}")
+        );
+        assert_eq!(healthcheck("docker run --health-cmd NONE nginx"), yaml("{test: [NONE]}"));
+        assert_eq!(
+            healthcheck("docker run --health-cmd 'curl x' --health-retries 3 --no-healthcheck nginx"),
+            yaml("{disable: true}")
+        );
+
+        let error = composerize("docker run --health-interval 30 nginx", "", "latest", 2).unwrap_err();
+        assert!(error.contains("Invalid duration '30': missing unit"));
+        let error = composerize(r#"docker run --health-cmd '["CMD", "curl"' nginx"#, "", "latest", 2).unwrap_err();
+        assert!(error.contains("Invalid exec-form health command"));
+    }
+
     #[test]
     fn test_labels() {
         let result = composerize("docker run -l app=web -l env=prod nginx", "", "latest", 2);
@@ -772,4 +1111,127 @@ services:
         assert!(yaml.contains("/src:/dst"));
         assert!(!yaml.contains(":ro"));
     }
+
+    #[test]
+    fn test_composerizer_options() {
+        let options = ComposerizeOptions::new()
+            .with_version(ComposeVersion::V3)
+            .with_env_style(EnvStyle::Map)
+            .with_command_style(CommandStyle::Auto);
+        let conversion = Composerizer::new(options)
+            .convert("docker run -e A=1 -e B --entrypoint /init nginx nginx -g daemon")
+            .unwrap();
+        let yaml = conversion.output;
+        assert!(yaml.starts_with("version: '3'"));
+        assert!(yaml.contains("environment:\n      A: '1'\n      B: null"));
+        assert!(yaml.contains("entrypoint: /init"));
+        assert!(yaml.contains("command: nginx -g daemon"));
+        assert!(conversion.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_warnings() {
+        let input = "docker run --no-such-flag nginx";
+        let lenient = Composerizer::default().convert(input).unwrap();
+        assert_eq!(lenient.warnings.len(), 1);
+
+        let strict = Composerizer::new(ComposerizeOptions::new().with_strictness(Strictness::Strict));
+        assert!(strict.convert(input).unwrap_err().contains("strict mode"));
+        assert!(strict
+            .convert("docker run --frobnicate=x nginx")
+            .unwrap_err()
+            .contains("Unknown flag --frobnicate"));
+    }
+
+    #[test]
+    fn test_merge_into_existing() {
+        let existing = "services:\n  nginx:\n    image: nginx:old\nvolumes:\n  data: {}\n";
+        let options = ComposerizeOptions::new().with_merge_target(existing);
+        let conversion = Composerizer::new(options)
+            .convert("docker run -v data:/data -v logs:/logs nginx")
+            .unwrap();
+        let compose: Value = serde_yaml::from_str(&conversion.output).unwrap();
+        assert_eq!(compose["services"]["nginx"]["image"], Value::from("nginx:old"));
+        assert_eq!(compose["services"]["nginx-2"]["image"], Value::from("nginx"));
+        assert_eq!(compose["volumes"]["data"], Value::Mapping(Default::default()));
+        assert!(compose["volumes"]["logs"].is_null());
+        assert_eq!(conversion.warnings, vec!["Service 'nginx' already exists; added as 'nginx-2'"]);
+
+        let json = composerize_to_json("docker run redis", r#"{"services": {"web": {"image": "nginx"}}}"#, "latest", 2).unwrap();
+        assert!(json.contains("\"web\"") && json.contains("\"redis\""));
+    }
+
+    #[test]
+    fn test_custom_mappings() {
+        let mut mappings = mappings::FlagMappings::new();
+        mappings
+            .extend_from_toml("[flags.team]\ntype = \"array\"\npath = \"labels\"\n[flags.verbose]\narity = \"switch\"\n")
+            .unwrap();
+        let conversion = Composerizer::new(ComposerizeOptions::new().with_mappings(mappings))
+            .convert("docker run --verbose --team=infra nginx")
+            .unwrap();
+        assert!(conversion.output.contains("labels:\n    - infra"));
+        assert!(conversion.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_long_port_syntax() {
+        let options = ComposerizeOptions::new().with_port_style(PortStyle::Long);
+        let conversion = Composerizer::new(options)
+            .convert("docker run -p 80 -p 8080:80 -p 127.0.0.1:53:53/udp -p [::1]::443 -p 9000-9001:9000-9001 nginx")
+            .unwrap();
+        let compose: Value = serde_yaml::from_str(&conversion.output).unwrap();
+        let ports = &compose["services"]["nginx"]["ports"];
+        assert_eq!(ports[0], serde_yaml::from_str::<Value>("target: 80").unwrap());
+        assert_eq!(ports[1], serde_yaml::from_str::<Value>("{target: 80, published: '8080'}").unwrap());
+        assert_eq!(
+            ports[2],
+            serde_yaml::from_str::<Value>("{target: 53, published: '53', host_ip: 127.0.0.1, protocol: udp}").unwrap()
+        );
+        assert_eq!(ports[3], serde_yaml::from_str::<Value>("{target: 443, host_ip: '::1'}").unwrap());
+        assert_eq!(ports[4], Value::from("9000-9001:9000-9001"));
+    }
+
+    #[test]
+    fn test_secret_patterns() {
+        let options = ComposerizeOptions::new()
+            .with_secret_patterns(vec!["*password*".to_string(), "API_?EY".to_string()]);
+        let yaml = Composerizer::new(options)
+            .convert("docker run -e DB_PASSWORD=hunter2 -e API_KEY=abc -e USER=me -e ROOT_PASSWORD=$PW nginx")
+            .unwrap()
+            .output;
+        assert!(yaml.contains("DB_PASSWORD=${DB_PASSWORD}"));
+        assert!(yaml.contains("API_KEY=${API_KEY}"));
+        assert!(yaml.contains("USER=me"));
+        assert!(yaml.contains("ROOT_PASSWORD=${PW}"));
+        assert!(!yaml.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("*TOKEN", "github_token"));
+        assert!(!wildcard_match("*TOKEN", "TOKENS"));
+        assert!(wildcard_match("a*b*c", "aXbYYc"));
+    }
+
+    #[test]
+    fn test_all_networks_declared() {
+        let yaml = composerize(
+            "docker run --network front --net name=back,alias=db --network none nginx",
+            "",
+            "latest",
+            2,
+        )
+        .unwrap();
+        let compose: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(compose["services"]["nginx"]["networks"]["back"]["aliases"][0], Value::from("db"));
+        let declared: Vec<&str> = compose["networks"]
+            .as_mapping()
+            .unwrap()
+            .keys()
+            .filter_map(|k| k.as_str())
+            .collect();
+        assert_eq!(declared, vec!["front", "back"]);
+    }
 }
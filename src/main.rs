@@ -1,5 +1,12 @@
-use clap::{Parser, Subcommand};
-use composerize_np::{composerize, composerize_to_json, convert_file, json_to_yaml, yaml_to_json};
+use clap::{Args, Parser, Subcommand};
+use composerize_np::config::Config;
+use composerize_np::image::ImageOptions;
+use composerize_np::naming::NamingPolicy;
+use composerize_np::options::{ComposeVersion, ComposerizeOptions, EnvStyle, OutputFormat, PortStyle, ResourcePolicy};
+use composerize_np::parser::CommandStyle;
+use composerize_np::shell::ShellDialect;
+use composerize_np::volume::BindRewrite;
+use composerize_np::{convert_file, explain, interactive, json_to_yaml, yaml_to_json, Composerizer};
 use std::fs;
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
@@ -13,32 +20,120 @@ struct Cli {
     command: Option<Commands>,
 
     /// Docker run command to convert (legacy mode, use 'run' subcommand instead)
-    #[arg(value_name = "COMMAND", conflicts_with = "command", conflicts_with = "from_file")]
+    #[arg(value_name = "COMMAND", conflicts_with = "from_file")]
     docker_command: Vec<String>,
 
-    /// Docker Compose format (v2x, v3x, latest)
-    #[arg(short, long, default_value = "latest")]
-    format: String,
+    /// Docker Compose format (v2x, v3x, latest) [default: latest]
+    #[arg(short, long)]
+    format: Option<ComposeVersion>,
 
-    /// Number of spaces for indentation
-    #[arg(short, long, default_value_t = 2)]
-    indent: usize,
+    /// Number of spaces for indentation [default: 2]
+    #[arg(short, long)]
+    indent: Option<usize>,
 
     /// Save to file (default: docker-compose.yml)
     #[arg(short, long, value_name = "FILE", num_args = 0..=1, default_missing_value = "docker-compose.yml", require_equals = false)]
     output: Option<PathBuf>,
 
-    /// Output format: yaml or json
-    #[arg(long, default_value = "yaml")]
-    output_format: String,
+    /// Output format: yaml or json [default: yaml]
+    #[arg(long)]
+    output_format: Option<OutputFormat>,
 
     /// Read docker command from file
-    #[arg(long, value_name = "FILE", conflicts_with = "command", conflicts_with = "docker_command")]
+    #[arg(long, value_name = "FILE", conflicts_with = "docker_command")]
     from_file: Option<PathBuf>,
-}
 
+    /// Service name to use instead of deriving it from --name or the image
+    #[arg(long, value_name = "NAME")]
+    service_name: Option<String>,
+
+    #[command(flatten)]
+    conversion: ConversionArgs,
+}
 
+/// Conversion settings shared by the legacy mode and the `run` subcommand.
+/// Settings left unset here come from the config file.
+#[derive(Args)]
+struct ConversionArgs {
+    /// Config file to use instead of .composerize.toml
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Service naming when --service-name is not given: container-name or image
+    #[arg(long, value_name = "POLICY", value_parser = parse_naming)]
+    naming: Option<NamingPolicy>,
+
+    /// Environment style: list (KEY=value) or map (KEY: value) [default: list]
+    #[arg(long, value_name = "STYLE")]
+    env_style: Option<EnvStyle>,
+
+    /// Port style: short ("8080:80") or long (target/published keys) [default: short]
+    #[arg(long, value_name = "STYLE")]
+    port_style: Option<PortStyle>,
+
+    /// Command style: exec (always a list) or auto (string when unambiguous) [default: exec]
+    #[arg(long, value_name = "STYLE")]
+    command_style: Option<CommandStyle>,
+
+    /// Fail instead of printing warnings
+    #[arg(long)]
+    strict: bool,
+
+    /// Do not infer depends_on from environment values naming another service
+    #[arg(long)]
+    no_depends_on: bool,
+
+    /// Network declarations: auto (from `docker network create` in the script), external or managed [default: auto]
+    #[arg(long, value_name = "POLICY")]
+    network_policy: Option<ResourcePolicy>,
+
+    /// Volume declarations: auto (from `docker volume create` in the script), external or managed [default: auto]
+    #[arg(long, value_name = "POLICY")]
+    volume_policy: Option<ResourcePolicy>,
+
+    /// Shell syntax of the command: posix, powershell or cmd [default: posix]
+    #[arg(long, value_name = "SHELL")]
+    shell: Option<ShellDialect>,
+
+    /// Environment variable pattern (e.g. '*PASSWORD*') whose value is replaced by ${NAME}; repeatable
+    #[arg(long = "secret-pattern", value_name = "PATTERN")]
+    secret_patterns: Vec<String>,
+
+    /// Substitute variables from the current environment instead of keeping ${VAR}
+    #[arg(long)]
+    expand_env: bool,
+
+    /// Drop docker.io/ and docker.io/library/ from the image
+    #[arg(long)]
+    normalize_image: bool,
+
+    /// Replace the image tag with ${VAR:-tag}
+    #[arg(long, value_name = "VAR")]
+    tag_variable: Option<String>,
+
+    /// Warn when the image is not pinned to a tag or digest
+    #[arg(long)]
+    warn_unpinned: bool,
+
+    /// Rewrite bind mounts below DIR (default: the output file's directory) to ./ paths
+    #[arg(long, value_name = "DIR", num_args = 0..=1, require_equals = true)]
+    relative_binds: Option<Option<PathBuf>>,
+
+    /// Write rewritten bind mounts as ${VAR}/... instead of ./...; implies --relative-binds
+    #[arg(long, value_name = "VAR")]
+    bind_variable: Option<String>,
+
+    /// Extra flag mappings (TOML, or YAML for any other extension)
+    #[arg(long, value_name = "FILE")]
+    mappings: Option<PathBuf>,
+
+    /// Print which compose key each flag produced (text or json) instead of the compose file
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, require_equals = true, default_missing_value = "text", value_parser = ["text", "json"])]
+    explain: Option<String>,
+}
 
+// Parsed once per run, so the size of the `run` variant does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Convert docker run command to compose file
@@ -47,25 +142,45 @@ enum Commands {
         #[arg(value_name = "COMMAND")]
         docker_command: Vec<String>,
 
-        /// Docker Compose format (v2x, v3x, latest)
-        #[arg(short, long, default_value = "latest")]
-        format: String,
+        /// Docker Compose format (v2x, v3x, latest) [default: latest]
+        #[arg(short, long)]
+        format: Option<ComposeVersion>,
 
-        /// Number of spaces for indentation
-        #[arg(short, long, default_value_t = 2)]
-        indent: usize,
+        /// Number of spaces for indentation [default: 2]
+        #[arg(short, long)]
+        indent: Option<usize>,
 
         /// Save to file (default: docker-compose.yml)
         #[arg(short, long, value_name = "FILE", num_args = 0..=1, default_missing_value = "docker-compose.yml", require_equals = false)]
         output: Option<PathBuf>,
 
-        /// Output format: yaml or json
-        #[arg(long, default_value = "yaml")]
-        output_format: String,
+        /// Output format: yaml or json [default: yaml]
+        #[arg(long)]
+        output_format: Option<OutputFormat>,
 
         /// Read docker command from file
         #[arg(long, value_name = "FILE", conflicts_with = "docker_command")]
         from_file: Option<PathBuf>,
+
+        /// Service name to use instead of deriving it from --name or the image
+        #[arg(long, value_name = "NAME")]
+        service_name: Option<String>,
+
+        #[command(flatten)]
+        conversion: ConversionArgs,
+    },
+
+    /// Review a conversion interactively before writing it
+    Interactive {
+        /// Config file to use instead of .composerize.toml
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+    },
+
+    /// Inspect the settings read from the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
     },
 
     /// Convert YAML to JSON
@@ -106,6 +221,24 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the effective settings, including defaults
+    Show {
+        /// Config file to use instead of .composerize.toml
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+    },
+}
+
+fn parse_naming(value: &str) -> Result<NamingPolicy, String> {
+    match value {
+        "container-name" => Ok(NamingPolicy::PreferContainerName),
+        "image" => Ok(NamingPolicy::Image),
+        _ => Err(format!("Unknown naming policy: {}", value)),
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
 
@@ -123,7 +256,19 @@ fn main() {
             cli.docker_command
         };
         
-        handle_docker_run(cmd, cli.format, cli.indent, cli.output, cli.output_format);
+        let explain = cli.conversion.explain.clone();
+        let options = conversion_options(
+            cli.format,
+            cli.indent,
+            cli.output_format,
+            cli.service_name,
+            cli.output.as_deref(),
+            cli.conversion,
+        );
+        match explain {
+            Some(format) => handle_explain(cmd, options, &format),
+            None => handle_docker_run(cmd, options, cli.output),
+        }
         return;
     }
 
@@ -136,6 +281,8 @@ fn main() {
             output,
             output_format,
             from_file,
+            service_name,
+            conversion,
         }) => {
             let cmd = if let Some(file_path) = from_file {
                 match fs::read_to_string(&file_path) {
@@ -148,7 +295,20 @@ fn main() {
             } else {
                 docker_command
             };
-            handle_docker_run(cmd, format, indent, output, output_format);
+            let explain = conversion.explain.clone();
+            let options = conversion_options(format, indent, output_format, service_name, output.as_deref(), conversion);
+            match explain {
+                Some(format) => handle_explain(cmd, options, &format),
+                None => handle_docker_run(cmd, options, output),
+            }
+        }
+        Some(Commands::Interactive { config }) => {
+            handle_interactive(config.as_deref());
+        }
+        Some(Commands::Config {
+            action: ConfigAction::Show { config },
+        }) => {
+            handle_config_show(config.as_deref());
         }
         Some(Commands::YamlToJson {
             input,
@@ -195,6 +355,26 @@ fn main() {
             println!("  -f, --format <VERSION>     Compose version: latest, v3x, v2x [default: latest]");
             println!("  -i, --indent <NUM>         Indentation spaces [default: 2]");
             println!("  --from-file <FILE>         Read docker command from file");
+            println!("  --service-name <NAME>      Service name (default: --name or image name)");
+            println!("  --naming <POLICY>          Service naming: container-name or image");
+            println!("  --env-style <STYLE>        Environment style: list or map [default: list]");
+            println!("  --port-style <STYLE>       Port style: short or long [default: short]");
+            println!("  --secret-pattern <PATTERN> Replace values of matching variables with ${{NAME}}");
+            println!("  --command-style <STYLE>    Command style: exec or auto [default: exec]");
+            println!("  --shell <SHELL>            Command syntax: posix, powershell, cmd [default: posix]");
+            println!("  --strict                   Fail instead of printing warnings");
+            println!("  --no-depends-on            Do not infer depends_on from environment values");
+            println!("  --network-policy <POLICY>  Networks: auto, external or managed [default: auto]");
+            println!("  --volume-policy <POLICY>   Volumes: auto, external or managed [default: auto]");
+            println!("  --expand-env               Substitute variables from the environment");
+            println!("  --normalize-image          Drop docker.io/library/ from the image");
+            println!("  --tag-variable <VAR>       Replace the image tag with ${{VAR:-tag}}");
+            println!("  --warn-unpinned            Warn when the image has no tag or digest");
+            println!("  --relative-binds[=DIR]     Rewrite bind mounts below DIR to ./ paths [default: output directory]");
+            println!("  --bind-variable <VAR>      Write rewritten bind mounts as ${{VAR}}/...");
+            println!("  --mappings <FILE>          Extra flag mappings (TOML or YAML)");
+            println!("  --config <FILE>            Config file [default: .composerize.toml]");
+            println!("  --explain[=FORMAT]         Show which key each flag produced (text or json)");
             println!("  -h, --help                 Print help\n");
             
             println!("SUBCOMMANDS:");
@@ -202,6 +382,8 @@ fn main() {
             println!("  yaml-to-json  Convert YAML to JSON");
             println!("  json-to-yaml  Convert JSON to YAML");
             println!("  convert       Auto-detect and convert between formats");
+            println!("  interactive   Review a conversion and its settings before writing it");
+            println!("  config show   Print the effective settings from the config file");
             println!("  help          Print this message or the help of the given subcommand(s)\n");
             
             println!("For detailed help on any subcommand:");
@@ -216,13 +398,119 @@ fn main() {
     }
 }
 
-fn handle_docker_run(
-    docker_command: Vec<String>,
-    format: String,
-    indent: usize,
-    output: Option<PathBuf>,
-    output_format: String,
-) {
+/// Reads the config file given with --config, or the discovered one
+fn load_config(path: Option<&Path>) -> Result<(Option<PathBuf>, Config), String> {
+    match path.map(Path::to_path_buf).or_else(Config::discover) {
+        Some(path) => Config::load(&path).map(|config| (Some(path), config)),
+        None => Ok((None, Config::default())),
+    }
+}
+
+fn conversion_options(
+    format: Option<ComposeVersion>,
+    indent: Option<usize>,
+    output_format: Option<OutputFormat>,
+    service_name: Option<String>,
+    output: Option<&Path>,
+    args: ConversionArgs,
+) -> Result<ComposerizeOptions, String> {
+    let (_, file_config) = load_config(args.config.as_deref())?;
+    let cli_config = Config {
+        format,
+        output_format,
+        indent,
+        naming: args.naming,
+        service_name,
+        env_style: args.env_style,
+        port_style: args.port_style,
+        command_style: args.command_style,
+        shell: args.shell,
+        strict: args.strict.then_some(true),
+        secret_patterns: (!args.secret_patterns.is_empty()).then_some(args.secret_patterns),
+        infer_depends_on: args.no_depends_on.then_some(false),
+        network_policy: args.network_policy,
+        volume_policy: args.volume_policy,
+        mappings: args.mappings,
+        flags: Default::default(),
+    };
+
+    let mut options = file_config.merge(cli_config).to_options()?.with_image_options(ImageOptions {
+        warn_unpinned: args.warn_unpinned,
+        normalize: args.normalize_image,
+        tag_variable: args.tag_variable,
+    });
+    if args.expand_env {
+        options = options.with_env(std::env::vars().collect());
+    }
+    if args.relative_binds.is_some() || args.bind_variable.is_some() {
+        let base = args
+            .relative_binds
+            .flatten()
+            .or_else(|| output.and_then(Path::parent).map(Path::to_path_buf))
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let base = std::path::absolute(base)
+            .map_err(|e| format!("Cannot resolve bind mount base directory: {}", e))?;
+        let mut rewrite = BindRewrite::new(base);
+        rewrite.variable = args.bind_variable;
+        options = options.with_bind_rewrite(rewrite);
+    }
+    Ok(options)
+}
+
+fn handle_explain(docker_command: Vec<String>, options: Result<ComposerizeOptions, String>, format: &str) {
+    let result = options
+        .and_then(|options| explain::explain_stack(&docker_command.join(" "), &options))
+        .and_then(|explanation| match format {
+            "json" => explanation.to_json(),
+            _ => Ok(explanation.to_text()),
+        });
+    match result {
+        Ok(output) => println!("{}", output.trim_end()),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn handle_interactive(path: Option<&Path>) {
+    let result = load_config(path)
+        .and_then(|(_, config)| config.to_options())
+        .and_then(|options| {
+            interactive::run(options, io::stdin().lock(), io::stdout().lock()).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn handle_config_show(path: Option<&Path>) {
+    let result = load_config(path).and_then(|(path, config)| Ok((path, config.effective().to_toml()?)));
+    match result {
+        Ok((path, toml)) => {
+            match path {
+                Some(path) => println!("# Settings from {}", path.display()),
+                None => println!("# No config file found; showing defaults"),
+            }
+            print!("{}", toml);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn handle_docker_run(docker_command: Vec<String>, options: Result<ComposerizeOptions, String>, output: Option<PathBuf>) {
+    let options = match options {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
     let command = docker_command.join(" ");
 
     let existing_compose = if atty::isnt(atty::Stream::Stdin) {
@@ -233,18 +521,22 @@ fn handle_docker_run(
         String::new()
     };
 
-    let result = if output_format == "json" {
-        composerize_to_json(&command, &existing_compose, &format, indent)
-    } else {
-        composerize(&command, &existing_compose, &format, indent)
-    };
+    let output_format = options.output_format;
+    let result = Composerizer::new(options.with_merge_target(&existing_compose)).convert(&command);
 
     match result {
-        Ok(output_content) => {
+        Ok(conversion) => {
+            for warning in &conversion.warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            for rewrite in &conversion.path_rewrites {
+                eprintln!("Rewrote bind mount {} of {} to {}", rewrite.from, rewrite.service, rewrite.to);
+            }
+            let output_content = conversion.output;
             if let Some(mut output_path) = output {
                 // If path is default name docker-compose.yml but format is JSON,
                 // change extension to .json
-                if output_path.to_str() == Some("docker-compose.yml") && output_format == "json" {
+                if output_path.to_str() == Some("docker-compose.yml") && output_format == OutputFormat::Json {
                     output_path = PathBuf::from("docker-compose.json");
                 }
 
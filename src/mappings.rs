@@ -1,7 +1,12 @@
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ArgType {
     Array,
     Ulimits,
@@ -14,64 +19,165 @@ pub enum ArgType {
     Networks,
     MapArray,
     Map,
+    /// One `KEY=VALUE` per flag, added to a map; the value may contain commas
+    KeyValue,
     Envs,
+    /// `--gpus` request, added to the device reservations
     Gpus,
+    /// Host device; CDI GPU names become device reservations
+    Device,
+    /// `-v` spec, validated and normalized to the short syntax
+    Volume,
+    /// Go duration such as `1m30s`, validated and normalized
+    Duration,
+    /// Whole seconds, written as a duration
+    Seconds,
+    /// Size such as `512m`, validated and normalized; `-1` is unlimited
+    ByteSize,
+    /// `--health-cmd`: shell form, an exec-form JSON array or `NONE`
+    HealthTest,
+    /// Exec-form argument list; the last occurrence wins
+    Command,
+    /// Changes the container but has no compose equivalent; dropped with a warning
+    Unsupported,
+}
+
+/// How a flag consumes its value on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Arity {
+    /// Boolean flag; a value can only be given as `--flag=false`
+    Switch,
+    /// Always takes a value, either `--flag value` or `--flag=value`
+    Required,
+    /// Takes a value only as `--flag=value`, defaults to `true` otherwise
+    Optional,
 }
 
 #[derive(Debug, Clone)]
 pub struct Mapping {
     pub arg_type: ArgType,
     pub path: String,
+    pub arity: Arity,
 }
 
 impl Mapping {
     pub fn new(arg_type: ArgType, path: &str) -> Self {
+        let arity = match arg_type {
+            ArgType::Switch => Arity::Switch,
+            _ => Arity::Required,
+        };
         Self {
             arg_type,
             path: path.to_string(),
+            arity,
+        }
+    }
+
+    /// A flag that is understood by the parser but has no compose equivalent
+    pub fn ignored(arity: Arity) -> Self {
+        let arg_type = match arity {
+            Arity::Switch => ArgType::Switch,
+            _ => ArgType::Value,
+        };
+        Self {
+            arg_type,
+            path: String::new(),
+            arity,
         }
     }
+
+    /// A flag that changes the container but that compose cannot express
+    pub fn unsupported(arity: Arity) -> Self {
+        Self {
+            arg_type: ArgType::Unsupported,
+            path: String::new(),
+            arity,
+        }
+    }
+
+    pub fn with_arity(mut self, arity: Arity) -> Self {
+        self.arity = arity;
+        self
+    }
+}
+
+/// Flag name without dashes to its mapping
+pub type MappingTable = IndexMap<String, Mapping>;
+
+/// Returns the built-in flag table, which is built on first use
+pub fn get_mappings() -> &'static MappingTable {
+    static MAPPINGS: OnceLock<MappingTable> = OnceLock::new();
+    MAPPINGS.get_or_init(builtin_mappings)
 }
 
-pub fn get_mappings() -> IndexMap<String, Mapping> {
+/// Docker CLI release whose `docker run` flags the built-in table covers
+pub const DOCKER_CLI_VERSION: &str = "28.3";
+
+/// Every flag of `docker run` in [`DOCKER_CLI_VERSION`], short ones included
+pub const DOCKER_RUN_FLAGS: &[&str] = &[
+    "add-host", "annotation", "attach", "a", "blkio-weight", "blkio-weight-device", "cap-add",
+    "cap-drop", "cgroup-parent", "cgroupns", "cidfile", "cpu-count", "cpu-percent", "cpu-period",
+    "cpu-quota", "cpu-rt-period", "cpu-rt-runtime", "cpu-shares", "c", "cpus", "cpuset-cpus",
+    "cpuset-mems", "detach", "d", "detach-keys", "device", "device-cgroup-rule", "device-read-bps",
+    "device-read-iops", "device-write-bps", "device-write-iops", "disable-content-trust", "dns",
+    "dns-opt", "dns-option", "dns-search", "domainname", "entrypoint", "env", "e", "env-file",
+    "expose", "gpus", "group-add", "health-cmd", "health-interval", "health-retries",
+    "health-start-interval", "health-start-period", "health-timeout", "help", "hostname", "h",
+    "init", "interactive", "i", "io-maxbandwidth", "io-maxiops", "ip", "ip6", "ipc", "isolation",
+    "kernel-memory", "label", "l", "label-file", "link", "link-local-ip", "log-driver", "log-opt",
+    "mac-address", "memory", "m", "memory-reservation", "memory-swap", "memory-swappiness",
+    "mount", "name", "network", "net", "network-alias", "net-alias", "no-healthcheck",
+    "oom-kill-disable", "oom-score-adj", "pid", "pids-limit", "platform", "privileged", "publish",
+    "p", "publish-all", "P", "pull", "quiet", "q", "read-only", "restart", "rm", "runtime",
+    "security-opt", "shm-size", "sig-proxy", "stop-signal", "stop-timeout", "storage-opt",
+    "sysctl", "tmpfs", "tty", "t", "ulimit", "use-api-socket", "user", "u", "userns", "uts",
+    "volume", "v", "volume-driver", "volumes-from", "workdir", "w",
+];
+
+fn builtin_mappings() -> MappingTable {
     let mut mappings = IndexMap::new();
 
     // Main mappings
     mappings.insert("add-host".to_string(), Mapping::new(ArgType::Array, "extra_hosts"));
+    mappings.insert("annotation".to_string(), Mapping::new(ArgType::Array, "annotations"));
     mappings.insert("blkio-weight".to_string(), Mapping::new(ArgType::IntValue, "blkio_config/weight"));
     mappings.insert("blkio-weight-device".to_string(), Mapping::new(ArgType::DeviceBlockIOConfigWeight, "blkio_config/weight_device"));
     mappings.insert("cap-add".to_string(), Mapping::new(ArgType::Array, "cap_add"));
     mappings.insert("cap-drop".to_string(), Mapping::new(ArgType::Array, "cap_drop"));
     mappings.insert("cgroup-parent".to_string(), Mapping::new(ArgType::Value, "cgroup_parent"));
     mappings.insert("cgroupns".to_string(), Mapping::new(ArgType::Value, "cgroup"));
-    mappings.insert("cpu-period".to_string(), Mapping::new(ArgType::Value, "cpu_period"));
-    mappings.insert("cpu-quota".to_string(), Mapping::new(ArgType::Value, "cpu_quota"));
-    mappings.insert("cpu-rt-period".to_string(), Mapping::new(ArgType::Value, "cpu_rt_period"));
-    mappings.insert("cpu-rt-runtime".to_string(), Mapping::new(ArgType::Value, "cpu_rt_runtime"));
+    mappings.insert("cpu-count".to_string(), Mapping::new(ArgType::IntValue, "cpu_count"));
+    mappings.insert("cpu-percent".to_string(), Mapping::new(ArgType::IntValue, "cpu_percent"));
+    mappings.insert("cpu-period".to_string(), Mapping::new(ArgType::IntValue, "cpu_period"));
+    mappings.insert("cpu-quota".to_string(), Mapping::new(ArgType::IntValue, "cpu_quota"));
+    mappings.insert("cpu-rt-period".to_string(), Mapping::new(ArgType::IntValue, "cpu_rt_period"));
+    mappings.insert("cpu-rt-runtime".to_string(), Mapping::new(ArgType::IntValue, "cpu_rt_runtime"));
     mappings.insert("cpu-shares".to_string(), Mapping::new(ArgType::IntValue, "cpu_shares"));
     mappings.insert("cpus".to_string(), Mapping::new(ArgType::FloatValue, "deploy/resources/limits/cpus"));
-    mappings.insert("detached".to_string(), Mapping::new(ArgType::Switch, ""));
+    mappings.insert("cpuset-cpus".to_string(), Mapping::new(ArgType::Value, "cpuset"));
     mappings.insert("device-cgroup-rule".to_string(), Mapping::new(ArgType::Array, "device_cgroup_rules"));
     mappings.insert("device-read-bps".to_string(), Mapping::new(ArgType::DeviceBlockIOConfigRate, "blkio_config/device_read_bps"));
     mappings.insert("device-read-iops".to_string(), Mapping::new(ArgType::DeviceBlockIOConfigRate, "blkio_config/device_read_iops"));
     mappings.insert("device-write-bps".to_string(), Mapping::new(ArgType::DeviceBlockIOConfigRate, "blkio_config/device_write_bps"));
     mappings.insert("device-write-iops".to_string(), Mapping::new(ArgType::DeviceBlockIOConfigRate, "blkio_config/device_write_iops"));
-    mappings.insert("device".to_string(), Mapping::new(ArgType::Array, "devices"));
+    mappings.insert("device".to_string(), Mapping::new(ArgType::Device, "devices"));
     mappings.insert("dns-opt".to_string(), Mapping::new(ArgType::Array, "dns_opt"));
     mappings.insert("dns-search".to_string(), Mapping::new(ArgType::Array, "dns_search"));
     mappings.insert("dns".to_string(), Mapping::new(ArgType::Array, "dns"));
     mappings.insert("domainname".to_string(), Mapping::new(ArgType::Value, "domainname"));
-    mappings.insert("entrypoint".to_string(), Mapping::new(ArgType::Array, "entrypoint"));
+    mappings.insert("entrypoint".to_string(), Mapping::new(ArgType::Command, "entrypoint"));
     mappings.insert("env-file".to_string(), Mapping::new(ArgType::Array, "env_file"));
     mappings.insert("env".to_string(), Mapping::new(ArgType::Envs, "environment"));
     mappings.insert("expose".to_string(), Mapping::new(ArgType::Array, "expose"));
-    mappings.insert("gpus".to_string(), Mapping::new(ArgType::Gpus, "deploy"));
+    mappings.insert("gpus".to_string(), Mapping::new(ArgType::Gpus, "deploy/resources/reservations/devices"));
     mappings.insert("group-add".to_string(), Mapping::new(ArgType::Array, "group_add"));
-    mappings.insert("health-cmd".to_string(), Mapping::new(ArgType::Value, "healthcheck/test"));
-    mappings.insert("health-interval".to_string(), Mapping::new(ArgType::Value, "healthcheck/interval"));
+    mappings.insert("health-cmd".to_string(), Mapping::new(ArgType::HealthTest, "healthcheck/test"));
+    mappings.insert("health-interval".to_string(), Mapping::new(ArgType::Duration, "healthcheck/interval"));
     mappings.insert("health-retries".to_string(), Mapping::new(ArgType::IntValue, "healthcheck/retries"));
-    mappings.insert("health-start-period".to_string(), Mapping::new(ArgType::Value, "healthcheck/start_period"));
-    mappings.insert("health-timeout".to_string(), Mapping::new(ArgType::Value, "healthcheck/timeout"));
+    mappings.insert("health-start-interval".to_string(), Mapping::new(ArgType::Duration, "healthcheck/start_interval"));
+    mappings.insert("health-start-period".to_string(), Mapping::new(ArgType::Duration, "healthcheck/start_period"));
+    mappings.insert("health-timeout".to_string(), Mapping::new(ArgType::Duration, "healthcheck/timeout"));
     mappings.insert("hostname".to_string(), Mapping::new(ArgType::Value, "hostname"));
     mappings.insert("init".to_string(), Mapping::new(ArgType::Switch, "init"));
     mappings.insert("interactive".to_string(), Mapping::new(ArgType::Switch, "stdin_open"));
@@ -80,15 +186,16 @@ pub fn get_mappings() -> IndexMap<String, Mapping> {
     mappings.insert("ipc".to_string(), Mapping::new(ArgType::Value, "ipc"));
     mappings.insert("isolation".to_string(), Mapping::new(ArgType::Value, "isolation"));
     mappings.insert("label".to_string(), Mapping::new(ArgType::Array, "labels"));
+    mappings.insert("label-file".to_string(), Mapping::new(ArgType::Array, "label_file"));
     mappings.insert("link-local-ip".to_string(), Mapping::new(ArgType::Array, "networks/¤network¤/link_local_ips"));
     mappings.insert("link".to_string(), Mapping::new(ArgType::Array, "links"));
     mappings.insert("log-driver".to_string(), Mapping::new(ArgType::Value, "logging/driver"));
-    mappings.insert("log-opt".to_string(), Mapping::new(ArgType::Map, "logging/options"));
+    mappings.insert("log-opt".to_string(), Mapping::new(ArgType::KeyValue, "logging/options"));
     mappings.insert("mac-address".to_string(), Mapping::new(ArgType::Value, "mac_address"));
-    mappings.insert("memory-reservation".to_string(), Mapping::new(ArgType::Value, "deploy/resources/reservations/memory"));
-    mappings.insert("memory-swap".to_string(), Mapping::new(ArgType::Value, "memswap_limit"));
-    mappings.insert("memory-swappiness".to_string(), Mapping::new(ArgType::Value, "mem_swappiness"));
-    mappings.insert("memory".to_string(), Mapping::new(ArgType::Value, "deploy/resources/limits/memory"));
+    mappings.insert("memory-reservation".to_string(), Mapping::new(ArgType::ByteSize, "deploy/resources/reservations/memory"));
+    mappings.insert("memory-swap".to_string(), Mapping::new(ArgType::ByteSize, "memswap_limit"));
+    mappings.insert("memory-swappiness".to_string(), Mapping::new(ArgType::IntValue, "mem_swappiness"));
+    mappings.insert("memory".to_string(), Mapping::new(ArgType::ByteSize, "deploy/resources/limits/memory"));
     mappings.insert("mount".to_string(), Mapping::new(ArgType::MapArray, "volumes"));
     mappings.insert("name".to_string(), Mapping::new(ArgType::Value, "container_name"));
     mappings.insert("net".to_string(), Mapping::new(ArgType::Networks, "network_mode"));
@@ -96,7 +203,7 @@ pub fn get_mappings() -> IndexMap<String, Mapping> {
     mappings.insert("network".to_string(), Mapping::new(ArgType::Networks, "network_mode"));
     mappings.insert("no-healthcheck".to_string(), Mapping::new(ArgType::Switch, "healthcheck/disable"));
     mappings.insert("oom-kill-disable".to_string(), Mapping::new(ArgType::Switch, "oom_kill_disable"));
-    mappings.insert("oom-score-adj".to_string(), Mapping::new(ArgType::Value, "oom_score_adj"));
+    mappings.insert("oom-score-adj".to_string(), Mapping::new(ArgType::IntValue, "oom_score_adj"));
     mappings.insert("pid".to_string(), Mapping::new(ArgType::Value, "pid"));
     mappings.insert("pids-limit".to_string(), Mapping::new(ArgType::IntValue, "deploy/resources/limits/pids"));
     mappings.insert("platform".to_string(), Mapping::new(ArgType::Value, "platform"));
@@ -108,21 +215,44 @@ pub fn get_mappings() -> IndexMap<String, Mapping> {
     mappings.insert("rm".to_string(), Mapping::new(ArgType::Switch, ""));
     mappings.insert("runtime".to_string(), Mapping::new(ArgType::Value, "runtime"));
     mappings.insert("security-opt".to_string(), Mapping::new(ArgType::Array, "security_opt"));
-    mappings.insert("shm-size".to_string(), Mapping::new(ArgType::Value, "shm_size"));
+    mappings.insert("shm-size".to_string(), Mapping::new(ArgType::ByteSize, "shm_size"));
     mappings.insert("stop-signal".to_string(), Mapping::new(ArgType::Value, "stop_signal"));
-    mappings.insert("stop-timeout".to_string(), Mapping::new(ArgType::Value, "stop_grace_period"));
-    mappings.insert("storage-opt".to_string(), Mapping::new(ArgType::Map, "storage_opt"));
+    mappings.insert("stop-timeout".to_string(), Mapping::new(ArgType::Seconds, "stop_grace_period"));
+    mappings.insert("storage-opt".to_string(), Mapping::new(ArgType::KeyValue, "storage_opt"));
     mappings.insert("sysctl".to_string(), Mapping::new(ArgType::Array, "sysctls"));
     mappings.insert("tmpfs".to_string(), Mapping::new(ArgType::Array, "tmpfs"));
     mappings.insert("tty".to_string(), Mapping::new(ArgType::Switch, "tty"));
     mappings.insert("ulimit".to_string(), Mapping::new(ArgType::Ulimits, "ulimits"));
+    mappings.insert("use-api-socket".to_string(), Mapping::new(ArgType::Switch, "use_api_socket"));
     mappings.insert("user".to_string(), Mapping::new(ArgType::Value, "user"));
     mappings.insert("userns".to_string(), Mapping::new(ArgType::Value, "userns_mode"));
     mappings.insert("uts".to_string(), Mapping::new(ArgType::Value, "uts"));
-    mappings.insert("volume".to_string(), Mapping::new(ArgType::Array, "volumes"));
+    mappings.insert("volume".to_string(), Mapping::new(ArgType::Volume, "volumes"));
+    mappings.insert("volume-driver".to_string(), Mapping::new(ArgType::Value, "volume_driver"));
     mappings.insert("volumes-from".to_string(), Mapping::new(ArgType::Array, "volumes_from"));
     mappings.insert("workdir".to_string(), Mapping::new(ArgType::Value, "working_dir"));
 
+    // Options of the docker client itself, with nothing to carry over
+    mappings.insert("detach".to_string(), Mapping::ignored(Arity::Switch));
+    mappings.insert("detach-keys".to_string(), Mapping::ignored(Arity::Required));
+    mappings.insert("help".to_string(), Mapping::ignored(Arity::Switch));
+    mappings.insert("quiet".to_string(), Mapping::ignored(Arity::Switch));
+    mappings.insert("sig-proxy".to_string(), Mapping::ignored(Arity::Switch));
+
+    // Flags without a compose equivalent, listed so their values are consumed
+    mappings.insert("attach".to_string(), Mapping::unsupported(Arity::Required));
+    mappings.insert("cidfile".to_string(), Mapping::unsupported(Arity::Required));
+    mappings.insert("cpuset-mems".to_string(), Mapping::unsupported(Arity::Required));
+    mappings.insert("disable-content-trust".to_string(), Mapping::unsupported(Arity::Switch));
+    mappings.insert("io-maxbandwidth".to_string(), Mapping::unsupported(Arity::Required));
+    mappings.insert("io-maxiops".to_string(), Mapping::unsupported(Arity::Required));
+    mappings.insert("kernel-memory".to_string(), Mapping::unsupported(Arity::Required));
+    mappings.insert("publish-all".to_string(), Mapping::unsupported(Arity::Switch));
+
+    // Long aliases
+    mappings.insert("dns-option".to_string(), mappings.get("dns-opt").unwrap().clone());
+    mappings.insert("net-alias".to_string(), mappings.get("network-alias").unwrap().clone());
+
     // Short flags
     mappings.insert("v".to_string(), mappings.get("volume").unwrap().clone());
     mappings.insert("p".to_string(), mappings.get("publish").unwrap().clone());
@@ -135,11 +265,109 @@ pub fn get_mappings() -> IndexMap<String, Mapping> {
     mappings.insert("t".to_string(), mappings.get("tty").unwrap().clone());
     mappings.insert("i".to_string(), mappings.get("interactive").unwrap().clone());
     mappings.insert("m".to_string(), mappings.get("memory").unwrap().clone());
-    mappings.insert("d".to_string(), mappings.get("detached").unwrap().clone());
+    mappings.insert("d".to_string(), mappings.get("detach").unwrap().clone());
+    mappings.insert("a".to_string(), mappings.get("attach").unwrap().clone());
+    mappings.insert("P".to_string(), mappings.get("publish-all").unwrap().clone());
+    mappings.insert("q".to_string(), mappings.get("quiet").unwrap().clone());
 
     mappings
 }
 
+/// The built-in table with user-defined flags added or overridden
+#[derive(Debug, Clone, Default)]
+pub struct FlagMappings {
+    overrides: MappingTable,
+}
+
+/// Layout of a mappings file:
+///
+/// ```toml
+/// [flags.team-id]
+/// type = "array"
+/// path = "labels"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MappingFile {
+    #[serde(default)]
+    flags: IndexMap<String, MappingEntry>,
+}
+
+/// One flag of a mappings file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MappingEntry {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub arg_type: Option<ArgType>,
+    /// Compose path; a flag without one is accepted and ignored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arity: Option<Arity>,
+}
+
+impl FlagMappings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Looks up a flag, preferring user-defined mappings
+    pub fn get(&self, flag: &str) -> Option<&Mapping> {
+        self.overrides.get(flag).or_else(|| get_mappings().get(flag))
+    }
+
+    /// Adds or replaces the mapping of a flag, given with or without dashes
+    pub fn insert(&mut self, flag: &str, mapping: Mapping) {
+        self.overrides.insert(flag.trim_start_matches('-').to_string(), mapping);
+    }
+
+    /// Adds the mappings of a TOML document
+    pub fn extend_from_toml(&mut self, content: &str) -> Result<(), String> {
+        let file: MappingFile = toml::from_str(content)
+            .map_err(|e| format!("Failed to parse mappings: {}", e))?;
+        self.extend(file.flags)
+    }
+
+    /// Adds the mappings of a YAML (or JSON) document
+    pub fn extend_from_yaml(&mut self, content: &str) -> Result<(), String> {
+        let file: MappingFile = serde_yaml::from_str(content)
+            .map_err(|e| format!("Failed to parse mappings: {}", e))?;
+        self.extend(file.flags)
+    }
+
+    /// Loads a mappings file, choosing TOML or YAML by its extension
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read mappings file {}: {}", path.display(), e))?;
+        let mut mappings = Self::new();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => mappings.extend_from_toml(&content)?,
+            _ => mappings.extend_from_yaml(&content)?,
+        }
+        Ok(mappings)
+    }
+
+    /// Adds mappings given as file entries, keyed by flag name
+    pub fn extend(&mut self, entries: IndexMap<String, MappingEntry>) -> Result<(), String> {
+        for (flag, entry) in entries {
+            let mapping = match (entry.arg_type, entry.path) {
+                (Some(arg_type), Some(path)) if !path.is_empty() => Mapping::new(arg_type, &path),
+                (Some(_), _) => return Err(format!("Mapping for '{}' has a type but no path", flag)),
+                (None, Some(path)) if !path.is_empty() => {
+                    return Err(format!("Mapping for '{}' has a path but no type", flag))
+                }
+                (None, _) => Mapping::ignored(entry.arity.unwrap_or(Arity::Required)),
+            };
+            let mapping = match entry.arity {
+                Some(arity) => mapping.with_arity(arity),
+                None => mapping,
+            };
+            self.insert(&flag, mapping);
+        }
+        Ok(())
+    }
+}
+
 pub fn strip_quotes(val: &str) -> String {
     let trimmed = val.trim();
     if (trimmed.starts_with('"') && trimmed.ends_with('"'))
@@ -158,24 +386,109 @@ pub fn parse_key_value_list(input: &str, list_sep: char, entry_sep: char) -> Ind
         let parts: Vec<&str> = item.splitn(2, entry_sep).collect();
         if parts.len() == 2 {
             let key = parts[0].trim().to_string();
-            let value = parts[1].trim();
-            
-            if let Ok(num) = value.parse::<i64>() {
-                result.insert(key, Value::Number(num.into()));
-            } else {
-                result.insert(key, Value::String(value.to_string()));
-            }
+            result.insert(key, scalar_value(parts[1].trim()));
         }
     }
     
     result
 }
 
+/// An integer if `value` is one, a string otherwise
+pub fn scalar_value(value: &str) -> Value {
+    match value.parse::<i64>() {
+        Ok(number) => Value::Number(number.into()),
+        Err(_) => Value::String(value.to_string()),
+    }
+}
+
 pub fn is_boolean_flag(flag: &str) -> bool {
-    let mappings = get_mappings();
-    if let Some(mapping) = mappings.get(flag) {
-        matches!(mapping.arg_type, ArgType::Switch)
+    if let Some(mapping) = get_mappings().get(flag) {
+        mapping.arity == Arity::Switch
     } else {
         false
     }
 }
+
+/// Parses a boolean flag value the way docker's flag library does
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "1" | "t" | "T" | "true" | "TRUE" | "True" => Some(true),
+        "0" | "f" | "F" | "false" | "FALSE" | "False" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_table_is_shared() {
+        assert!(std::ptr::eq(get_mappings(), get_mappings()));
+        assert!(is_boolean_flag("privileged"));
+        assert!(!is_boolean_flag("publish"));
+    }
+
+    #[test]
+    fn test_table_covers_docker_run() {
+        let missing: Vec<&str> = DOCKER_RUN_FLAGS
+            .iter()
+            .copied()
+            .filter(|flag| !get_mappings().contains_key(*flag))
+            .collect();
+        assert!(missing.is_empty(), "flags of docker {} missing: {:?}", DOCKER_CLI_VERSION, missing);
+
+        let extra: Vec<&String> = get_mappings()
+            .keys()
+            .filter(|flag| !DOCKER_RUN_FLAGS.contains(&flag.as_str()))
+            .collect();
+        assert!(extra.is_empty(), "flags not in docker {}: {:?}", DOCKER_CLI_VERSION, extra);
+    }
+
+    #[test]
+    fn test_toml_mappings() {
+        let mut mappings = FlagMappings::new();
+        mappings
+            .extend_from_toml(
+                r#"
+                [flags.team]
+                type = "array"
+                path = "labels"
+
+                [flags."--hostname"]
+                type = "value"
+                path = "domainname"
+
+                [flags.trace]
+                arity = "switch"
+                "#,
+            )
+            .unwrap();
+
+        assert_eq!(mappings.get("team").unwrap().path, "labels");
+        assert_eq!(mappings.get("hostname").unwrap().path, "domainname");
+        let trace = mappings.get("trace").unwrap();
+        assert!(trace.path.is_empty());
+        assert_eq!(trace.arity, Arity::Switch);
+        assert_eq!(mappings.get("publish").unwrap().path, "ports");
+        assert_eq!(get_mappings()["hostname"].path, "hostname");
+    }
+
+    #[test]
+    fn test_yaml_mappings() {
+        let mut mappings = FlagMappings::new();
+        mappings
+            .extend_from_yaml("flags:\n  gpu-count:\n    type: int_value\n    path: x-gpu-count\n")
+            .unwrap();
+        assert!(matches!(mappings.get("gpu-count").unwrap().arg_type, ArgType::IntValue));
+    }
+
+    #[test]
+    fn test_invalid_mappings() {
+        let mut mappings = FlagMappings::new();
+        assert!(mappings.extend_from_toml("[flags.x]\ntype = \"array\"\n").is_err());
+        assert!(mappings.extend_from_toml("[flags.x]\npath = \"labels\"\n").is_err());
+        assert!(mappings.extend_from_toml("[flags.x]\ntype = \"nope\"\npath = \"a\"\n").is_err());
+        assert!(mappings.extend_from_yaml("flags:\n  x:\n    paht: a\n").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Service name derivation.
+//!
+//! Compose service names end up in DNS and container names, so whatever the
+//! source (`--name`, the image or an explicit override) is reduced to
+//! lowercase letters, digits, `-` and `_`.
+
+use serde::{Deserialize, Serialize};
+
+/// How the service key of a converted command is chosen
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NamingPolicy {
+    /// Use `--name` when given, otherwise the image repository name
+    #[default]
+    #[serde(rename = "container-name")]
+    PreferContainerName,
+    /// Always use the image repository name
+    Image,
+    /// Use this name
+    Fixed(String),
+}
+
+/// Fallback when nothing usable remains after sanitizing
+const DEFAULT_SERVICE_NAME: &str = "app";
+
+/// Picks the service name for a container according to `policy`.
+pub fn service_name(image: &str, container_name: Option<&str>, policy: &NamingPolicy) -> String {
+    let raw = match policy {
+        NamingPolicy::Fixed(name) => name.as_str(),
+        NamingPolicy::PreferContainerName => match container_name {
+            Some(name) if !name.trim().is_empty() => name,
+            _ => image_name(image),
+        },
+        NamingPolicy::Image => image_name(image),
+    };
+    sanitize_service_name(raw)
+}
+
+/// Returns the repository name of an image reference, without registry,
+/// namespace, tag or digest: `localhost:5000/team/app:1.0@sha256:...` gives `app`.
+pub fn image_name(image: &str) -> &str {
+    let without_digest = image.split('@').next().unwrap_or(image);
+    let last = without_digest.rsplit('/').next().unwrap_or(without_digest);
+    last.split(':').next().unwrap_or(last)
+}
+
+/// Reduces a name to the characters allowed in a compose service name.
+pub fn sanitize_service_name(name: &str) -> String {
+    let mut result = String::new();
+    for ch in name.trim().chars() {
+        let ch = ch.to_ascii_lowercase();
+        if ch.is_ascii_alphanumeric() || ch == '_' {
+            result.push(ch);
+        } else if !result.ends_with('-') {
+            // Dots, spaces and anything else become a single dash
+            result.push('-');
+        }
+    }
+
+    let trimmed = result.trim_matches(|c| c == '-' || c == '_');
+    if trimmed.is_empty() {
+        DEFAULT_SERVICE_NAME.to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_name() {
+        assert_eq!(image_name("nginx"), "nginx");
+        assert_eq!(image_name("nginx:1.25"), "nginx");
+        assert_eq!(image_name("nginx@sha256:abc123"), "nginx");
+        assert_eq!(image_name("localhost:5000/app"), "app");
+        assert_eq!(image_name("localhost:5000/team/app:1.0@sha256:abc"), "app");
+        assert_eq!(image_name("localhost:5000"), "localhost");
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize_service_name("My.App"), "my-app");
+        assert_eq!(sanitize_service_name("web_1"), "web_1");
+        assert_eq!(sanitize_service_name("--x..y--"), "x-y");
+        assert_eq!(sanitize_service_name("/"), "app");
+    }
+
+    #[test]
+    fn test_policies() {
+        let image = "registry.example.com/Team/API.Server:2";
+        assert_eq!(
+            service_name(image, Some("backend"), &NamingPolicy::PreferContainerName),
+            "backend"
+        );
+        assert_eq!(service_name(image, None, &NamingPolicy::PreferContainerName), "api-server");
+        assert_eq!(service_name(image, Some("backend"), &NamingPolicy::Image), "api-server");
+        assert_eq!(
+            service_name(image, Some("backend"), &NamingPolicy::Fixed("My Svc".to_string())),
+            "my-svc"
+        );
+    }
+}
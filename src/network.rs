@@ -0,0 +1,186 @@
+//! Top-level network definitions from `docker network create`.
+
+use crate::parser::{parse_subcommand_args, SubcommandFlag};
+use serde_yaml::{Mapping, Value};
+
+const FLAGS: &[SubcommandFlag] = &[
+    SubcommandFlag { long: "driver", short: Some('d'), takes_value: true },
+    SubcommandFlag { long: "subnet", short: None, takes_value: true },
+    SubcommandFlag { long: "gateway", short: None, takes_value: true },
+    SubcommandFlag { long: "ip-range", short: None, takes_value: true },
+    SubcommandFlag { long: "aux-address", short: None, takes_value: true },
+    SubcommandFlag { long: "ipam-driver", short: None, takes_value: true },
+    SubcommandFlag { long: "ipam-opt", short: None, takes_value: true },
+    SubcommandFlag { long: "opt", short: Some('o'), takes_value: true },
+    SubcommandFlag { long: "label", short: None, takes_value: true },
+    SubcommandFlag { long: "internal", short: None, takes_value: false },
+    SubcommandFlag { long: "attachable", short: None, takes_value: false },
+    SubcommandFlag { long: "ipv6", short: None, takes_value: false },
+    SubcommandFlag { long: "ipv4", short: None, takes_value: false },
+    // Swarm settings without a compose equivalent
+    SubcommandFlag { long: "ingress", short: None, takes_value: false },
+    SubcommandFlag { long: "scope", short: None, takes_value: true },
+    SubcommandFlag { long: "config-from", short: None, takes_value: true },
+    SubcommandFlag { long: "config-only", short: None, takes_value: false },
+];
+
+/// A network created by a `docker network create` command
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CreatedNetwork {
+    pub name: String,
+    /// Entry for the top-level `networks` section
+    pub definition: Mapping,
+    /// Problems that did not prevent conversion
+    pub warnings: Vec<String>,
+}
+
+/// Parses the arguments after `network create`
+pub fn parse_network_create(tokens: &[String]) -> Result<CreatedNetwork, String> {
+    let (args, positional) = parse_subcommand_args(tokens, FLAGS, "network create")?;
+    let name = match positional.as_slice() {
+        [name] => name.clone(),
+        [] => return Err("network create requires a network name".to_string()),
+        [_, extra, ..] => return Err(format!("Unexpected argument '{}' for network create", extra)),
+    };
+
+    let mut definition = Mapping::new();
+    let mut warnings = Vec::new();
+    let values = |flag: &str| args.get(flag).map(Vec::as_slice).unwrap_or_default();
+    let enabled = |flag: &str| values(flag).last().is_some_and(|value| value == "true");
+
+    if let Some(driver) = values("driver").last() {
+        definition.insert(Value::from("driver"), Value::from(driver.as_str()));
+    }
+    if let Some(opts) = key_values(values("opt"), "--opt")? {
+        definition.insert(Value::from("driver_opts"), opts);
+    }
+    if let Some(ipam) = ipam(&args)? {
+        definition.insert(Value::from("ipam"), ipam);
+    }
+    for (flag, key) in [("internal", "internal"), ("attachable", "attachable"), ("ipv6", "enable_ipv6")] {
+        if enabled(flag) {
+            definition.insert(Value::from(key), Value::Bool(true));
+        }
+    }
+    if values("ipv4").last().is_some_and(|value| value == "false") {
+        definition.insert(Value::from("enable_ipv4"), Value::Bool(false));
+    }
+    if let Some(labels) = key_values(values("label"), "--label")? {
+        definition.insert(Value::from("labels"), labels);
+    }
+
+    for flag in ["ingress", "scope", "config-from", "config-only"] {
+        if args.contains_key(flag) {
+            warnings.push(format!("Ignoring --{} of network '{}': it has no compose equivalent", flag, name));
+        }
+    }
+
+    Ok(CreatedNetwork { name, definition, warnings })
+}
+
+/// The `ipam` block: each `--gateway` and `--ip-range` belongs to the
+/// `--subnet` at the same position, `--aux-address` to the first one
+fn ipam(args: &crate::parser::ParsedArgs) -> Result<Option<Value>, String> {
+    let values = |flag: &str| args.get(flag).map(Vec::as_slice).unwrap_or_default();
+    let subnets = values("subnet");
+    for flag in ["gateway", "ip-range"] {
+        if values(flag).len() > subnets.len() {
+            return Err(format!("Each --{} needs a --subnet", flag));
+        }
+    }
+
+    let mut configs: Vec<Value> = subnets
+        .iter()
+        .enumerate()
+        .map(|(i, subnet)| {
+            let mut config = Mapping::new();
+            config.insert(Value::from("subnet"), Value::from(subnet.as_str()));
+            for (flag, key) in [("gateway", "gateway"), ("ip-range", "ip_range")] {
+                if let Some(value) = values(flag).get(i) {
+                    config.insert(Value::from(key), Value::from(value.as_str()));
+                }
+            }
+            Value::Mapping(config)
+        })
+        .collect();
+    if let Some(aux) = key_values(values("aux-address"), "--aux-address")? {
+        match configs.first_mut() {
+            Some(Value::Mapping(config)) => {
+                config.insert(Value::from("aux_addresses"), aux);
+            }
+            _ => return Err("--aux-address needs a --subnet".to_string()),
+        }
+    }
+
+    let mut ipam = Mapping::new();
+    if let Some(driver) = values("ipam-driver").last() {
+        ipam.insert(Value::from("driver"), Value::from(driver.as_str()));
+    }
+    if !configs.is_empty() {
+        ipam.insert(Value::from("config"), Value::Sequence(configs));
+    }
+    if let Some(options) = key_values(values("ipam-opt"), "--ipam-opt")? {
+        ipam.insert(Value::from("options"), options);
+    }
+    Ok((!ipam.is_empty()).then_some(Value::Mapping(ipam)))
+}
+
+/// `KEY=value` flag values as a mapping; a bare `KEY` maps to an empty string
+pub(crate) fn key_values(values: &[String], flag: &str) -> Result<Option<Value>, String> {
+    let mut map = Mapping::new();
+    for value in values {
+        let (key, value) = value.split_once('=').unwrap_or((value, ""));
+        if key.is_empty() {
+            return Err(format!("Invalid {} value '{}': expected KEY=value", flag, value));
+        }
+        map.insert(Value::from(key), Value::from(value));
+    }
+    Ok((!map.is_empty()).then_some(Value::Mapping(map)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<CreatedNetwork, String> {
+        parse_network_create(&crate::shell::tokenize(args).unwrap())
+    }
+
+    #[test]
+    fn test_full_definition() {
+        let network = parse(
+            "-d bridge --subnet 10.1.0.0/16 --gateway 10.1.0.1 --ip-range 10.1.5.0/24 \
+             --subnet fd00::/64 --aux-address host1=10.1.0.5 --opt com.docker.network.bridge.name=br1 \
+             --internal --attachable --ipv6 --label team=core backend",
+        )
+        .unwrap();
+        let expected: Mapping = serde_yaml::from_str(
+            "driver: bridge\n\
+             driver_opts: {com.docker.network.bridge.name: br1}\n\
+             ipam:\n  config:\n\
+             \x20   - {subnet: 10.1.0.0/16, gateway: 10.1.0.1, ip_range: 10.1.5.0/24, aux_addresses: {host1: 10.1.0.5}}\n\
+             \x20   - {subnet: 'fd00::/64'}\n\
+             internal: true\n\
+             attachable: true\n\
+             enable_ipv6: true\n\
+             labels: {team: core}\n",
+        )
+        .unwrap();
+        assert_eq!(network.name, "backend");
+        assert_eq!(network.definition, expected);
+        assert!(network.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_errors_and_warnings() {
+        assert!(parse("").unwrap_err().contains("requires a network name"));
+        assert!(parse("--frob x").unwrap_err().contains("Unknown flag --frob"));
+        assert!(parse("--gateway 10.0.0.1 x").unwrap_err().contains("needs a --subnet"));
+        assert!(parse("a b").unwrap_err().contains("Unexpected argument 'b'"));
+
+        let network = parse("--scope swarm --ipam-driver default --ipam-opt a=1 x").unwrap();
+        assert_eq!(network.warnings, vec!["Ignoring --scope of network 'x': it has no compose equivalent"]);
+        assert_eq!(network.definition["ipam"]["driver"], Value::from("default"));
+        assert_eq!(network.definition["ipam"]["options"]["a"], Value::from("1"));
+    }
+}
@@ -0,0 +1,348 @@
+//! Conversion settings for [`crate::Composerizer`].
+
+use crate::image::ImageOptions;
+use crate::interpolation::InterpolationOptions;
+use crate::mappings::FlagMappings;
+use crate::naming::NamingPolicy;
+use crate::parser::{CommandStyle, ParseOptions};
+use crate::shell::ShellDialect;
+use crate::volume::BindRewrite;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Compose file format version to target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ComposeVersion {
+    /// Compose specification, no `version` key
+    #[default]
+    #[serde(rename = "latest")]
+    Latest,
+    /// `version: '2'`
+    #[serde(rename = "v2x")]
+    V2,
+    /// `version: '3'`
+    #[serde(rename = "v3x")]
+    V3,
+}
+
+impl ComposeVersion {
+    /// Value of the top-level `version` key, if any
+    pub fn version_key(&self) -> Option<&'static str> {
+        match self {
+            ComposeVersion::Latest => None,
+            ComposeVersion::V2 => Some("2"),
+            ComposeVersion::V3 => Some("3"),
+        }
+    }
+}
+
+impl FromStr for ComposeVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(ComposeVersion::Latest),
+            "v2x" => Ok(ComposeVersion::V2),
+            "v3x" => Ok(ComposeVersion::V3),
+            _ => Err(format!("Unknown format: {}", s)),
+        }
+    }
+}
+
+/// Serialization of the generated compose file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    #[serde(alias = "yml")]
+    Yaml,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Unknown output format: {}", s)),
+        }
+    }
+}
+
+/// How `environment` entries are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvStyle {
+    /// `- KEY=value`
+    #[default]
+    List,
+    /// `KEY: value`
+    Map,
+}
+
+impl FromStr for EnvStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "list" => Ok(EnvStyle::List),
+            "map" => Ok(EnvStyle::Map),
+            _ => Err(format!("Unknown env style: {}", s)),
+        }
+    }
+}
+
+/// How `ports` entries are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortStyle {
+    /// `"8080:80/udp"`
+    #[default]
+    Short,
+    /// `target`, `published`, `host_ip` and `protocol` keys; entries the
+    /// long syntax cannot express, such as container port ranges, stay short
+    Long,
+}
+
+impl FromStr for PortStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "short" => Ok(PortStyle::Short),
+            "long" => Ok(PortStyle::Long),
+            _ => Err(format!("Unknown port style: {}", s)),
+        }
+    }
+}
+
+impl FromStr for CommandStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exec" => Ok(CommandStyle::Exec),
+            "auto" => Ok(CommandStyle::Auto),
+            _ => Err(format!("Unknown command style: {}", s)),
+        }
+    }
+}
+
+impl FromStr for ShellDialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "posix" | "sh" | "bash" => Ok(ShellDialect::Posix),
+            "powershell" | "pwsh" => Ok(ShellDialect::PowerShell),
+            "cmd" => Ok(ShellDialect::Cmd),
+            _ => Err(format!("Unknown shell dialect: {}", s)),
+        }
+    }
+}
+
+/// How networks and volumes used by the services are declared at the top level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourcePolicy {
+    /// Defined from `docker network create` (or `volume create`) commands in
+    /// the same script; any other network is external, any other volume is
+    /// created by compose
+    #[default]
+    Auto,
+    /// `external: true`, created outside the compose file
+    External,
+    /// Created by compose, with the definition from the script if there is one
+    Managed,
+}
+
+impl FromStr for ResourcePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ResourcePolicy::Auto),
+            "external" => Ok(ResourcePolicy::External),
+            "managed" => Ok(ResourcePolicy::Managed),
+            _ => Err(format!("Unknown resource policy: {}", s)),
+        }
+    }
+}
+
+/// What to do with problems that do not prevent a conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// Report them as warnings
+    #[default]
+    Lenient,
+    /// Fail the conversion
+    Strict,
+}
+
+/// Settings for a conversion, built with the `with_*` methods
+#[derive(Debug, Clone)]
+pub struct ComposerizeOptions {
+    pub version: ComposeVersion,
+    pub output_format: OutputFormat,
+    pub indent: usize,
+    pub naming: NamingPolicy,
+    pub env_style: EnvStyle,
+    pub port_style: PortStyle,
+    /// Environment variable names, with `*` and `?` wildcards, whose values
+    /// are replaced by `${NAME}` so they stay out of the compose file
+    pub secret_patterns: Vec<String>,
+    pub command_style: CommandStyle,
+    pub strictness: Strictness,
+    pub dialect: ShellDialect,
+    pub interpolation: InterpolationOptions,
+    pub image: ImageOptions,
+    /// Flag table, with any user-defined mappings
+    pub mappings: FlagMappings,
+    /// Existing compose file (YAML or JSON) the result is merged into
+    pub merge_target: Option<String>,
+    /// Add `depends_on` for environment values naming another service of a
+    /// stack; links and other explicit references always add one
+    pub infer_depends_on: bool,
+    pub network_policy: ResourcePolicy,
+    pub volume_policy: ResourcePolicy,
+    /// Rewrite absolute bind mounts below a directory to relative paths
+    pub bind_rewrite: Option<BindRewrite>,
+}
+
+impl Default for ComposerizeOptions {
+    fn default() -> Self {
+        Self {
+            version: ComposeVersion::default(),
+            output_format: OutputFormat::default(),
+            indent: 2,
+            naming: NamingPolicy::default(),
+            env_style: EnvStyle::default(),
+            port_style: PortStyle::default(),
+            secret_patterns: Vec::new(),
+            command_style: CommandStyle::default(),
+            strictness: Strictness::default(),
+            dialect: ShellDialect::default(),
+            interpolation: InterpolationOptions::default(),
+            image: ImageOptions::default(),
+            mappings: FlagMappings::default(),
+            merge_target: None,
+            infer_depends_on: true,
+            network_policy: ResourcePolicy::default(),
+            volume_policy: ResourcePolicy::default(),
+            bind_rewrite: None,
+        }
+    }
+}
+
+impl ComposerizeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_version(mut self, version: ComposeVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn with_output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    pub fn with_naming(mut self, naming: NamingPolicy) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    pub fn with_env_style(mut self, env_style: EnvStyle) -> Self {
+        self.env_style = env_style;
+        self
+    }
+
+    pub fn with_port_style(mut self, port_style: PortStyle) -> Self {
+        self.port_style = port_style;
+        self
+    }
+
+    pub fn with_secret_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.secret_patterns = patterns;
+        self
+    }
+
+    pub fn with_command_style(mut self, command_style: CommandStyle) -> Self {
+        self.command_style = command_style;
+        self
+    }
+
+    pub fn with_strictness(mut self, strictness: Strictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    pub fn with_dialect(mut self, dialect: ShellDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Expands variables from `env` instead of emitting `${VAR}`
+    pub fn with_env(mut self, env: IndexMap<String, String>) -> Self {
+        self.interpolation.env = Some(env);
+        self
+    }
+
+    pub fn with_image_options(mut self, image: ImageOptions) -> Self {
+        self.image = image;
+        self
+    }
+
+    pub fn with_mappings(mut self, mappings: FlagMappings) -> Self {
+        self.mappings = mappings;
+        self
+    }
+
+    /// Merges the result into an existing compose file; empty input is ignored
+    pub fn with_merge_target(mut self, existing: &str) -> Self {
+        self.merge_target = if existing.trim().is_empty() {
+            None
+        } else {
+            Some(existing.to_string())
+        };
+        self
+    }
+
+    pub fn with_infer_depends_on(mut self, infer: bool) -> Self {
+        self.infer_depends_on = infer;
+        self
+    }
+
+    pub fn with_network_policy(mut self, policy: ResourcePolicy) -> Self {
+        self.network_policy = policy;
+        self
+    }
+
+    pub fn with_volume_policy(mut self, policy: ResourcePolicy) -> Self {
+        self.volume_policy = policy;
+        self
+    }
+
+    pub fn with_bind_rewrite(mut self, rewrite: BindRewrite) -> Self {
+        self.bind_rewrite = Some(rewrite);
+        self
+    }
+
+    pub(crate) fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            dialect: self.dialect,
+            interpolation: self.interpolation.clone(),
+            mappings: self.mappings.clone(),
+        }
+    }
+}
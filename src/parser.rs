@@ -1,159 +1,485 @@
-use crate::mappings::{get_mappings, strip_quotes, parse_key_value_list, is_boolean_flag, ArgType};
+use crate::explain::display_flag;
+use crate::interpolation::{render_word, InterpolationOptions};
+use crate::gpu::{add_cdi_device, is_cdi_gpu, parse_gpu_request};
+use crate::invocation::{parse_invocation, Invocation};
+use crate::network::{parse_network_create, CreatedNetwork};
+use crate::volume::{parse_volume_create, parse_volume_spec, CreatedVolume};
+use crate::mappings::{parse_bool, parse_key_value_list, scalar_value, Arity, ArgType, FlagMappings};
+use crate::shell::{split_command, split_commands, CommandLine, ShellDialect};
+use crate::units::{normalize_byte_size, normalize_duration, seconds_to_duration};
 use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
 
-pub fn parse_docker_command(input: &str) -> Result<(String, Vec<String>, IndexMap<String, Vec<String>>), String> {
-    // Handle bash-style line continuation (backslash + newline)
-    let cleaned = input
-        .trim()
-        .replace("\\\n", " ")  // Bash-style: \ + newline
-        .replace("\\\r\n", " ") // Windows-style: \ + CRLF
-        .replace('\n', " ")
-        .split_whitespace()
-        .collect::<Vec<_>>()
-        .join(" ");
-
-    // Remove docker/podman run/create
-    let re = regex::Regex::new(r"^(?:docker|podman)\s+(?:run|create|container\s+run|service\s+create)\s+")
-        .map_err(|e| format!("Regex error: {}", e))?;
-    
-    let cleaned = re.replace(&cleaned, "").to_string();
-    
-    let mut args: IndexMap<String, Vec<String>> = IndexMap::new();
-    let mut positional = Vec::new();
-    let mut tokens: Vec<String> = Vec::new();
+/// Flag values collected by [`parse_docker_command`], keyed by flag name without dashes
+pub type ParsedArgs = IndexMap<String, Vec<String>>;
+
+/// How `command` and `entrypoint` are written to the compose file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandStyle {
+    /// Always a sequence with one item per argument
+    #[default]
+    Exec,
+    /// A plain string when every argument is a short, simple word that
+    /// compose would split back identically, a sequence otherwise
+    Auto,
+}
+
+/// Options controlling how a command line is read
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub dialect: ShellDialect,
+    pub interpolation: InterpolationOptions,
+    pub mappings: FlagMappings,
+}
+
+/// A docker run command split into its parts
+#[derive(Debug, Clone, Default)]
+pub struct ParsedCommand {
+    pub image: String,
+    pub command: Vec<String>,
+    pub args: ParsedArgs,
+    /// Runtime, subcommand and global options in front of the arguments
+    pub invocation: Invocation,
+    /// Problems that did not prevent conversion
+    pub warnings: Vec<String>,
+}
+
+pub fn parse_docker_command(input: &str) -> Result<(String, Vec<String>, ParsedArgs), String> {
+    let parsed = parse_command(input, &ParseOptions::default())?;
+    Ok((parsed.image, parsed.command, parsed.args))
+}
+
+/// One command of a script
+#[derive(Debug, Clone)]
+pub enum StackCommand {
+    Run(ParsedCommand),
+    CreateNetwork(CreatedNetwork),
+    CreateVolume(CreatedVolume),
+}
+
+/// Parses a docker run command, rendering shell substitutions into compose
+/// interpolation and collecting warnings along the way
+pub fn parse_command(input: &str, options: &ParseOptions) -> Result<ParsedCommand, String> {
+    match parse_words(&split_command(input, options.dialect)?, options)? {
+        StackCommand::Run(parsed) => Ok(parsed),
+        StackCommand::CreateNetwork(_) | StackCommand::CreateVolume(_) => Err(
+            "Network and volume create commands can only be converted together with a run command".to_string(),
+        ),
+    }
+}
+
+/// Parses a script of docker run and resource commands, see [`split_commands`]
+pub fn parse_stack(input: &str, options: &ParseOptions) -> Result<Vec<StackCommand>, String> {
+    let commands = split_commands(input, options.dialect)?;
+    if commands.is_empty() {
+        return Err("No command specified".to_string());
+    }
+    commands.iter().map(|command| parse_words(command, options)).collect()
+}
+
+fn parse_words(command: &CommandLine, options: &ParseOptions) -> Result<StackCommand, String> {
+    let mut warnings = Vec::new();
+    for redirection in &command.redirections {
+        warnings.push(format!(
+            "Ignoring shell redirection '{}': it applies to the docker client, not the container",
+            redirection
+        ));
+    }
+    let tokens: Vec<String> = command
+        .words
+        .iter()
+        .map(|word| render_word(word, &options.interpolation, &mut warnings))
+        .collect();
+    let invocation = parse_invocation(&tokens, &mut warnings)?;
+    let tokens = &tokens[invocation.consumed..];
+
+    match invocation.subcommand.first().map(String::as_str) {
+        Some("network") => {
+            let mut network = parse_network_create(tokens)?;
+            warnings.append(&mut network.warnings);
+            network.warnings = warnings;
+            return Ok(StackCommand::CreateNetwork(network));
+        }
+        Some("volume") => {
+            let mut volume = parse_volume_create(tokens)?;
+            warnings.append(&mut volume.warnings);
+            volume.warnings = warnings;
+            return Ok(StackCommand::CreateVolume(volume));
+        }
+        _ => {}
+    }
+    parse_run(tokens, invocation, warnings, options).map(StackCommand::Run)
+}
+
+/// Parses the `[OPTIONS] IMAGE [COMMAND] [ARG...]` of docker run
+fn parse_run(
+    tokens: &[String],
+    invocation: Invocation,
+    mut warnings: Vec<String>,
+    options: &ParseOptions,
+) -> Result<ParsedCommand, String> {
+    let mappings = &options.mappings;
+    let mut args: ParsedArgs = IndexMap::new();
+    let mut image = None;
+    let mut command = Vec::new();
     
-    // Improved tokenizer with escaping support
-    let mut current = String::new();
-    let mut in_quotes = false;
-    let mut quote_char = ' ';
-    let mut chars = cleaned.chars().peekable();
+    let mut state = State::Options;
+    let mut i = 0;
     
-    while let Some(ch) = chars.next() {
-        match ch {
-            '\\' => {
-                // Escaping - add next character as is
-                if let Some(next_ch) = chars.next() {
-                    current.push(next_ch);
-                }
+    while i < tokens.len() {
+        let token = &tokens[i];
+        let rest = &tokens[i + 1..];
+        
+        match state {
+            State::Options if token == "--" => {
+                state = State::Image;
+                i += 1;
             }
-            '"' | '\'' if !in_quotes => {
-                in_quotes = true;
-                quote_char = ch;
-                current.push(ch);
+            State::Options if token.starts_with("--") => {
+                i += 1 + parse_long_flag(&token[2..], rest, mappings, &mut args, &mut warnings)?;
             }
-            c if c == quote_char && in_quotes => {
-                in_quotes = false;
-                current.push(ch);
+            State::Options if is_short_flag(token) => {
+                i += 1 + parse_short_flags(&token[1..], rest, mappings, &mut args, &mut warnings)?;
             }
-            c if c.is_whitespace() && !in_quotes => {
-                if !current.is_empty() {
-                    tokens.push(current.clone());
-                    current.clear();
+            State::Options | State::Image => {
+                image = Some(token.clone());
+                state = State::Command;
+                i += 1;
+                // A `--` right after the image only separates the command
+                if tokens.get(i).map(|t| t.as_str()) == Some("--") {
+                    i += 1;
                 }
             }
-            _ => {
-                current.push(ch);
+            State::Command => {
+                command.push(token.clone());
+                i += 1;
             }
         }
     }
     
-    if !current.is_empty() {
-        tokens.push(current);
+    let image = image.ok_or("No image specified")?;
+    drop_disabled_healthcheck(&mut args, &mut warnings);
+    drop_negative_seconds(&mut args, mappings, &mut warnings);
+    warn_unsupported(&args, mappings, &mut warnings);
+
+    Ok(ParsedCommand { image, command, args, invocation, warnings })
+}
+
+/// Position of the run-arguments parser within `[OPTIONS] IMAGE [COMMAND] [ARG...]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Reading flags until the first positional argument
+    Options,
+    /// After `--`: the next token is the image even if it looks like a flag
+    Image,
+    /// After the image: everything is passed to the container
+    Command,
+}
+
+fn is_short_flag(token: &str) -> bool {
+    let mut chars = token.chars();
+    // Negative numbers and a lone `-` are positional
+    chars.next() == Some('-') && matches!(chars.next(), Some(c) if !c.is_numeric())
+}
+
+/// Parses `--flag`, `--flag=value` or `--flag value`, returning how many of
+/// the following tokens were consumed as the value
+fn parse_long_flag(
+    flag: &str,
+    rest: &[String],
+    mappings: &FlagMappings,
+    args: &mut ParsedArgs,
+    warnings: &mut Vec<String>,
+) -> Result<usize, String> {
+    if let Some((name, value)) = flag.split_once('=') {
+        let value = match mappings.get(name) {
+            Some(mapping) if mapping.arity == Arity::Switch => switch_value(name, value)?,
+            Some(_) => value.to_string(),
+            None => {
+                warnings.push(format!("Unknown flag --{} with value '{}'", name, value));
+                value.to_string()
+            }
+        };
+        args.entry(name.to_string()).or_default().push(value);
+        return Ok(0);
     }
-    
-    let mut i = 0;
-    
-    while i < tokens.len() {
-        let token = &tokens[i];
-        
-        if token.starts_with("--") {
-            let flag_part = token.trim_start_matches("--");
-            
-            // Check for --flag=value format
-            if flag_part.contains('=') {
-                let parts: Vec<&str> = flag_part.splitn(2, '=').collect();
-                if parts.len() == 2 {
-                    args.entry(parts[0].to_string())
-                        .or_insert_with(Vec::new)
-                        .push(strip_quotes(parts[1]));
+
+    match mappings.get(flag).map(|m| m.arity) {
+        Some(Arity::Switch) | Some(Arity::Optional) => {
+            args.entry(flag.to_string()).or_default().push("true".to_string());
+            Ok(0)
+        }
+        Some(Arity::Required) => {
+            let value = rest
+                .first()
+                .ok_or_else(|| format!("Flag --{} requires a value", flag))?;
+            args.entry(flag.to_string()).or_default().push(value.clone());
+            Ok(1)
+        }
+        None => Ok(parse_unknown_flag(&format!("--{}", flag), flag, rest, args, warnings)),
+    }
+}
+
+/// Parses a cluster of short flags such as `-it`, `-p80:80` or `-e=A=b`,
+/// returning how many of the following tokens were consumed as a value
+fn parse_short_flags(
+    cluster: &str,
+    rest: &[String],
+    mappings: &FlagMappings,
+    args: &mut ParsedArgs,
+    warnings: &mut Vec<String>,
+) -> Result<usize, String> {
+    for (pos, ch) in cluster.char_indices() {
+        let name = ch.to_string();
+        let attached = &cluster[pos + ch.len_utf8()..];
+
+        match mappings.get(&name).map(|m| m.arity) {
+            Some(Arity::Switch) | Some(Arity::Optional) => {
+                let value = match attached.strip_prefix('=') {
+                    Some(value) => switch_value(&name, value)?,
+                    None => "true".to_string(),
+                };
+                args.entry(name).or_default().push(value);
+                if attached.starts_with('=') {
+                    return Ok(0);
                 }
-                i += 1;
-            } else if is_boolean_flag(flag_part) {
-                // Boolean flag
-                args.entry(flag_part.to_string())
-                    .or_insert_with(Vec::new)
-                    .push("true".to_string());
-                i += 1;
-            } else if i + 1 < tokens.len() && !tokens[i + 1].starts_with('-') {
-                // Flag with value via space
-                args.entry(flag_part.to_string())
-                    .or_insert_with(Vec::new)
-                    .push(strip_quotes(&tokens[i + 1]));
-                i += 2;
-            } else {
-                // Unknown flag without value - treat as boolean
-                args.entry(flag_part.to_string())
-                    .or_insert_with(Vec::new)
-                    .push("true".to_string());
-                i += 1;
             }
-        } else if token.starts_with('-') && token.len() > 1 && !token.chars().nth(1).unwrap().is_numeric() {
-            let flags = token.trim_start_matches('-');
-            
-            // If it's a single character, check for value
-            if flags.len() == 1 {
-                if i + 1 < tokens.len() && !tokens[i + 1].starts_with('-') {
-                    args.entry(flags.to_string())
-                        .or_insert_with(Vec::new)
-                        .push(strip_quotes(&tokens[i + 1]));
-                    i += 2;
-                } else {
-                    args.entry(flags.to_string())
-                        .or_insert_with(Vec::new)
-                        .push("true".to_string());
-                    i += 1;
+            Some(Arity::Required) => {
+                // The rest of the cluster is the value, otherwise the next token is
+                if !attached.is_empty() {
+                    let value = attached.strip_prefix('=').unwrap_or(attached);
+                    args.entry(name).or_default().push(value.to_string());
+                    return Ok(0);
                 }
-            } else {
-                // Multiple boolean flags (e.g., -it)
-                for flag_char in flags.chars() {
-                    args.entry(flag_char.to_string())
-                        .or_insert_with(Vec::new)
-                        .push("true".to_string());
-                }
-                i += 1;
+                let value = rest
+                    .first()
+                    .ok_or_else(|| format!("Flag -{} requires a value", ch))?;
+                args.entry(name).or_default().push(value.clone());
+                return Ok(1);
+            }
+            None if attached.is_empty() => {
+                return Ok(parse_unknown_flag(&format!("-{}", ch), &name, rest, args, warnings));
             }
+            None => {
+                warnings.push(format!("Unknown flag -{} treated as a switch", ch));
+                args.entry(name).or_default().push("true".to_string());
+            }
+        }
+    }
+    Ok(0)
+}
+
+/// A flag of a resource command such as `network create`
+pub(crate) struct SubcommandFlag {
+    pub long: &'static str,
+    pub short: Option<char>,
+    pub takes_value: bool,
+}
+
+/// Parses the arguments of a resource command with a fixed set of flags into
+/// values keyed by long flag name, and the positional arguments
+pub(crate) fn parse_subcommand_args(
+    tokens: &[String],
+    flags: &[SubcommandFlag],
+    command: &str,
+) -> Result<(ParsedArgs, Vec<String>), String> {
+    let mut args = ParsedArgs::new();
+    let mut positional = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+        if token == "--" {
+            positional.extend(tokens[i..].iter().cloned());
+            break;
+        }
+
+        let (flag, attached) = if let Some(long) = token.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            (flags.iter().find(|flag| flag.long == name), value)
+        } else if is_short_flag(token) {
+            let mut chars = token[1..].chars();
+            let short = chars.next();
+            let rest = chars.as_str();
+            let value = (!rest.is_empty()).then(|| rest.strip_prefix('=').unwrap_or(rest).to_string());
+            (flags.iter().find(|flag| flag.short.is_some() && flag.short == short), value)
         } else {
-            // This is image or command
-            positional.push(strip_quotes(token));
-            i += 1;
-            
-            // Everything else is command
-            while i < tokens.len() {
-                positional.push(strip_quotes(&tokens[i]));
+            positional.push(token.clone());
+            continue;
+        };
+
+        let flag = flag.ok_or_else(|| format!("Unknown flag {} for {}", token, command))?;
+        let value = match (flag.takes_value, attached) {
+            (true, Some(value)) => value,
+            (true, None) => {
+                let value = tokens
+                    .get(i)
+                    .ok_or_else(|| format!("Flag --{} requires a value", flag.long))?;
                 i += 1;
+                value.clone()
             }
-            break;
+            (false, Some(value)) => switch_value(flag.long, &value)?,
+            (false, None) => "true".to_string(),
+        };
+        args.entry(flag.long.to_string()).or_default().push(value);
+    }
+    Ok((args, positional))
+}
+
+/// Guesses the arity of a flag missing from the mapping table: it takes the
+/// next token as its value only if another token remains for the image
+fn parse_unknown_flag(
+    display: &str,
+    name: &str,
+    rest: &[String],
+    args: &mut ParsedArgs,
+    warnings: &mut Vec<String>,
+) -> usize {
+    match rest {
+        [value, _, ..] if !value.starts_with('-') => {
+            warnings.push(format!(
+                "Unknown flag {} assumed to take the value '{}'",
+                display, value
+            ));
+            args.entry(name.to_string()).or_default().push(value.clone());
+            1
+        }
+        _ => {
+            warnings.push(format!("Unknown flag {} treated as a switch", display));
+            args.entry(name.to_string()).or_default().push("true".to_string());
+            0
         }
     }
-    
-    let image = positional.first().ok_or("No image specified")?.clone();
-    let command = positional.into_iter().skip(1).collect();
-    
-    Ok((image, command, args))
+}
+
+fn switch_value(name: &str, value: &str) -> Result<String, String> {
+    parse_bool(value)
+        .map(|b| b.to_string())
+        .ok_or_else(|| format!("Invalid boolean value '{}' for flag {}", value, name))
+}
+
+/// Network substituted for `¤network¤` in mapping paths: the first
+/// `--network`, or `default`. Like docker, `--ip`, `--network-alias` and
+/// similar flags apply to that network only.
+pub fn primary_network(args: &ParsedArgs) -> String {
+    network_values(args)
+        .next()
+        .and_then(|value| parse_network_spec(value).ok())
+        .map(|network| network.name)
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Values of every `--network` and `--net` flag, in command line order
+pub fn network_values(args: &ParsedArgs) -> impl Iterator<Item = &String> {
+    args.iter()
+        .filter(|(key, _)| *key == "network" || *key == "net")
+        .flat_map(|(_, values)| values)
+}
+
+/// A `--network` value: a network or mode name, or docker's long syntax
+/// `name=NET,alias=A,ip=IP,...` with per-network settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkSpec {
+    pub name: String,
+    /// Settings in compose form, e.g. `ipv4_address` or `aliases`
+    pub settings: serde_yaml::Mapping,
+}
+
+pub fn parse_network_spec(value: &str) -> Result<NetworkSpec, String> {
+    let mut settings = serde_yaml::Mapping::new();
+    if !value.contains('=') {
+        return Ok(NetworkSpec { name: value.to_string(), settings });
+    }
+
+    let mut name = None;
+    for field in value.split(',') {
+        let (key, field_value) = field
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid --network option '{}': expected key=value", field))?;
+        match key {
+            "name" => name = Some(field_value.to_string()),
+            "alias" => push_setting(&mut settings, "aliases", Value::String(field_value.to_string())),
+            "link-local-ip" => push_setting(&mut settings, "link_local_ips", Value::String(field_value.to_string())),
+            "ip" | "ip6" | "mac-address" => {
+                let key = match key {
+                    "ip" => "ipv4_address",
+                    "ip6" => "ipv6_address",
+                    _ => "mac_address",
+                };
+                settings.insert(Value::from(key), Value::String(field_value.to_string()));
+            }
+            "driver-opt" => {
+                let (opt, opt_value) = field_value.split_once('=').unwrap_or((field_value, ""));
+                let opts = nested_mapping(&mut settings, "driver_opts");
+                opts.insert(Value::from(opt), Value::String(opt_value.to_string()));
+            }
+            "gw-priority" | "priority" => {
+                let priority: i64 = field_value
+                    .parse()
+                    .map_err(|_| format!("Invalid --network {} '{}'", key, field_value))?;
+                settings.insert(Value::from(key.replace('-', "_")), Value::from(priority));
+            }
+            _ => return Err(format!("Unknown --network option '{}'", key)),
+        }
+    }
+
+    let name = name.ok_or_else(|| format!("Missing name in --network '{}'", value))?;
+    Ok(NetworkSpec { name, settings })
+}
+
+/// Returns true for `--network` values that become `network_mode`
+pub fn is_network_mode(name: &str) -> bool {
+    name.contains(':') || ["host", "bridge", "none"].contains(&name)
+}
+
+fn push_setting(settings: &mut serde_yaml::Mapping, key: &str, value: Value) {
+    let entry = settings
+        .entry(Value::from(key))
+        .or_insert_with(|| Value::Sequence(Vec::new()));
+    if let Value::Sequence(items) = entry {
+        items.push(value);
+    }
+}
+
+/// Returns the mapping under `key`, replacing anything else stored there
+fn nested_mapping<'a>(map: &'a mut serde_yaml::Mapping, key: &str) -> &'a mut serde_yaml::Mapping {
+    let entry = map
+        .entry(Value::from(key))
+        .or_insert_with(|| Value::Mapping(serde_yaml::Mapping::new()));
+    if !entry.is_mapping() {
+        *entry = Value::Mapping(serde_yaml::Mapping::new());
+    }
+    match entry {
+        Value::Mapping(nested) => nested,
+        _ => unreachable!("entry was just made a mapping"),
+    }
 }
 
 pub fn build_compose_value(
     args: &IndexMap<String, Vec<String>>,
     network: &str,
 ) -> Result<Value, String> {
-    let mappings = get_mappings();
+    build_compose_value_with(args, network, &FlagMappings::default())
+}
+
+/// Builds the service definition using `mappings` instead of the built-in table
+pub fn build_compose_value_with(
+    args: &IndexMap<String, Vec<String>>,
+    network: &str,
+    mappings: &FlagMappings,
+) -> Result<Value, String> {
     let mut service = serde_yaml::Mapping::new();
     
     for (key, values) in args {
         if let Some(mapping) = mappings.get(key) {
             if mapping.path.is_empty() {
-                continue; // Ignore (e.g., --rm, --detached)
+                continue; // Ignore (e.g., --rm, --detach)
             }
             
             for value in values {
@@ -166,7 +492,7 @@ pub fn build_compose_value(
     Ok(Value::Mapping(service))
 }
 
-fn apply_mapping(
+pub(crate) fn apply_mapping(
     service: &mut serde_yaml::Mapping,
     path: &str,
     value: &str,
@@ -183,17 +509,23 @@ fn apply_mapping(
             set_nested_value(service, &parts, Value::Bool(bool_val));
         }
         ArgType::Value => {
-            // Special handling for healthcheck test
-            if parts.len() > 0 && parts[parts.len() - 1] == "test" && parts.contains(&"healthcheck") {
-                // Convert to CMD-SHELL format
-                let test_array = vec![
-                    Value::String("CMD-SHELL".to_string()),
-                    Value::String(value.to_string())
-                ];
-                set_nested_value(service, &parts, Value::Sequence(test_array));
-            } else {
-                set_nested_value(service, &parts, Value::String(value.to_string()));
-            }
+            set_nested_value(service, &parts, Value::String(value.to_string()));
+        }
+        ArgType::Duration => {
+            set_nested_value(service, &parts, Value::String(normalize_duration(value)?));
+        }
+        ArgType::Seconds => {
+            set_nested_value(service, &parts, Value::String(seconds_to_duration(value)?));
+        }
+        ArgType::ByteSize => {
+            let size = match value {
+                "-1" => Value::Number((-1).into()),
+                _ => Value::String(normalize_byte_size(value)?),
+            };
+            set_nested_value(service, &parts, size);
+        }
+        ArgType::HealthTest => {
+            set_nested_value(service, &parts, health_test(value)?);
         }
         ArgType::IntValue => {
             let int_val = value.parse::<i64>()
@@ -205,14 +537,12 @@ fn apply_mapping(
                 .map_err(|_| format!("Invalid float: {}", value))?;
             set_nested_value(service, &parts, Value::Number(serde_yaml::Number::from(float_val)));
         }
+        ArgType::Volume => {
+            let spec = parse_volume_spec(value)?;
+            set_nested_array(service, &parts, &spec.to_short_syntax());
+        }
         ArgType::Envs => {
-            let env_value = if value.contains('=') {
-                let parts: Vec<&str> = value.splitn(2, '=').collect();
-                format!("{}={}", parts[0], strip_quotes(parts[1]))
-            } else {
-                value.to_string()
-            };
-            set_nested_array(service, &parts, &env_value);
+            set_nested_array(service, &parts, value);
         }
         ArgType::Map => {
             let map = parse_key_value_list(value, ',', '=');
@@ -235,36 +565,153 @@ fn apply_mapping(
             }
         }
         ArgType::Networks => {
-            if value.matches(|c| c == ':').count() == 0 
-                && !["host", "bridge", "none"].contains(&value) 
-                && !value.starts_with("container:") {
-                // Named network
-                let mut network_map = IndexMap::new();
-                network_map.insert(
-                    Value::String(value.to_string()),
-                    Value::Mapping(serde_yaml::Mapping::new())
-                );
-                set_nested_value(service, &["networks"], Value::Mapping(network_map.into_iter().collect()));
+            let network = parse_network_spec(value)?;
+            if is_network_mode(&network.name) {
+                set_nested_value(service, &["network_mode"], Value::String(network.name));
             } else {
-                // network_mode
-                set_nested_value(service, &["network_mode"], Value::String(value.to_string()));
+                // Each --network adds to the service's networks; settings from
+                // flags such as --ip may already be there
+                let networks = nested_mapping(service, "networks");
+                let attachment = nested_mapping(networks, &network.name);
+                for (key, value) in network.settings {
+                    match (attachment.get_mut(&key), value) {
+                        (Some(Value::Sequence(existing)), Value::Sequence(items)) => existing.extend(items),
+                        (Some(Value::Mapping(existing)), Value::Mapping(entries)) => existing.extend(entries),
+                        (_, value) => {
+                            attachment.insert(key, value);
+                        }
+                    }
+                }
             }
         }
+        ArgType::Unsupported => {}
+        ArgType::Command => {
+            // docker takes --entrypoint as a single executable, not a shell string
+            let args = if value.is_empty() { Vec::new() } else { vec![value.to_string()] };
+            set_nested_value(service, &parts, command_value(&args, CommandStyle::Exec));
+        }
         ArgType::Ulimits => {
             parse_ulimit(service, &parts, value)?;
         }
         ArgType::Gpus => {
-            parse_gpus(service, value)?;
+            push_nested_value(service, &parts, parse_gpu_request(value)?);
+        }
+        ArgType::Device => {
+            if is_cdi_gpu(value) {
+                let reservations = ["deploy", "resources", "reservations"]
+                    .into_iter()
+                    .fold(service, |map, key| nested_mapping(map, key));
+                let devices = reservations
+                    .entry(Value::from("devices"))
+                    .or_insert_with(|| Value::Sequence(Vec::new()));
+                if let Value::Sequence(devices) = devices {
+                    add_cdi_device(devices, value);
+                }
+            } else {
+                set_nested_array(service, &parts, value);
+            }
+        }
+        ArgType::KeyValue => {
+            let (key, entry) = value
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid option '{}': expected KEY=VALUE", value))?;
+            let mut map = IndexMap::new();
+            map.insert(key.to_string(), scalar_value(entry));
+            set_nested_map(service, &parts, map);
         }
         ArgType::DeviceBlockIOConfigRate | ArgType::DeviceBlockIOConfigWeight => {
-            // Эти типы обрабатываются в оригинале, но пока упрощаем
-            set_nested_value(service, &parts, Value::String(value.to_string()));
+            push_nested_value(service, &parts, blkio_device(value, arg_type, path)?);
         }
     }
     
     Ok(())
 }
 
+/// `healthcheck.test` for a `--health-cmd` value. A JSON array is taken as
+/// the exec form, with `CMD` added unless it starts with a test type.
+fn health_test(value: &str) -> Result<Value, String> {
+    let test: Vec<String> = if value.trim() == "NONE" {
+        vec!["NONE".to_string()]
+    } else if value.trim_start().starts_with('[') {
+        let mut test: Vec<String> = serde_json::from_str(value)
+            .map_err(|e| format!("Invalid exec-form health command '{}': {}", value, e))?;
+        if !matches!(test.first().map(String::as_str), Some("CMD" | "CMD-SHELL" | "NONE")) {
+            test.insert(0, "CMD".to_string());
+        }
+        if test.len() == 1 && test[0] != "NONE" {
+            return Err(format!("Health command '{}' has no arguments", value));
+        }
+        test
+    } else {
+        vec!["CMD-SHELL".to_string(), value.to_string()]
+    };
+    Ok(Value::Sequence(test.into_iter().map(Value::String).collect()))
+}
+
+/// `--no-healthcheck` wins over the other health flags, since compose rejects
+/// `disable: true` next to them
+fn drop_disabled_healthcheck(args: &mut ParsedArgs, warnings: &mut Vec<String>) {
+    if args.get("no-healthcheck").and_then(|values| values.last()).map(String::as_str) != Some("true") {
+        return;
+    }
+    let conflicting: Vec<String> = args.keys().filter(|flag| flag.starts_with("health-")).cloned().collect();
+    for flag in conflicting {
+        args.shift_remove(&flag);
+        warnings.push(format!("Ignoring --{}: --no-healthcheck disables the healthcheck", flag));
+    }
+}
+
+/// A negative `--stop-timeout` makes docker wait forever, which compose
+/// cannot express
+fn drop_negative_seconds(args: &mut ParsedArgs, mappings: &FlagMappings, warnings: &mut Vec<String>) {
+    for (flag, values) in args.iter_mut() {
+        if mappings.get(flag).map(|m| &m.arg_type) != Some(&ArgType::Seconds) {
+            continue;
+        }
+        values.retain(|value| {
+            let negative = value.parse::<i64>().is_ok_and(|seconds| seconds < 0);
+            if negative {
+                warnings.push(format!(
+                    "Ignoring --{} {}: waiting forever has no compose equivalent",
+                    flag, value
+                ));
+            }
+            !negative
+        });
+    }
+    args.retain(|_, values| !values.is_empty());
+}
+
+/// Flags that change the container but that compose cannot express are
+/// left out with a warning
+fn warn_unsupported(args: &ParsedArgs, mappings: &FlagMappings, warnings: &mut Vec<String>) {
+    for (flag, values) in args {
+        let unsupported = mappings.get(flag).map(|m| &m.arg_type) == Some(&ArgType::Unsupported);
+        if unsupported && values.iter().any(|value| value != "false") {
+            warnings.push(format!("Ignoring {}: it is not supported by compose", display_flag(flag)));
+        }
+    }
+}
+
+/// Builds a `command`/`entrypoint` value that preserves every argument exactly
+pub fn command_value(args: &[String], style: CommandStyle) -> Value {
+    const SIMPLE_MAX_ARGS: usize = 4;
+
+    let simple = args.len() <= SIMPLE_MAX_ARGS
+        && args.iter().all(|arg| {
+            !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "_./:=@%+,-".contains(c))
+        });
+
+    if style == CommandStyle::Auto && simple && !args.is_empty() {
+        Value::String(args.join(" "))
+    } else {
+        Value::Sequence(args.iter().map(|arg| Value::String(arg.clone())).collect())
+    }
+}
+
 fn set_nested_value(map: &mut serde_yaml::Mapping, path: &[&str], value: Value) {
     if path.is_empty() {
         return;
@@ -285,6 +732,10 @@ fn set_nested_value(map: &mut serde_yaml::Mapping, path: &[&str], value: Value)
 }
 
 fn set_nested_array(map: &mut serde_yaml::Mapping, path: &[&str], value: &str) {
+    push_nested_value(map, path, Value::String(value.to_string()));
+}
+
+fn push_nested_value(map: &mut serde_yaml::Mapping, path: &[&str], value: Value) {
     if path.is_empty() {
         return;
     }
@@ -295,7 +746,7 @@ fn set_nested_array(map: &mut serde_yaml::Mapping, path: &[&str], value: &str) {
             .or_insert_with(|| Value::Sequence(Vec::new()));
         
         if let Value::Sequence(seq) = arr {
-            seq.push(Value::String(value.to_string()));
+            seq.push(value);
         }
         return;
     }
@@ -305,10 +756,35 @@ fn set_nested_array(map: &mut serde_yaml::Mapping, path: &[&str], value: &str) {
         .or_insert_with(|| Value::Mapping(serde_yaml::Mapping::new()));
     
     if let Value::Mapping(nested_map) = nested {
-        set_nested_array(nested_map, &path[1..], value);
+        push_nested_value(nested_map, &path[1..], value);
     }
 }
 
+/// `blkio_config` entry for `DEVICE:RATE` or `DEVICE:WEIGHT`; rates of `_bps`
+/// paths are sizes, the others plain numbers
+fn blkio_device(value: &str, arg_type: &ArgType, path: &str) -> Result<Value, String> {
+    let (device, amount) = value
+        .rsplit_once(':')
+        .filter(|(device, amount)| !device.is_empty() && !amount.is_empty())
+        .ok_or_else(|| format!("Invalid device limit '{}': expected DEVICE:VALUE", value))?;
+    let integer = |amount: &str| {
+        amount
+            .parse::<u64>()
+            .map(|n| Value::Number(n.into()))
+            .map_err(|_| format!("Invalid integer: {}", amount))
+    };
+    let (key, amount) = match arg_type {
+        ArgType::DeviceBlockIOConfigWeight => ("weight", integer(amount)?),
+        _ if path.ends_with("_bps") => ("rate", Value::String(normalize_byte_size(amount)?)),
+        _ => ("rate", integer(amount)?),
+    };
+
+    let mut entry = serde_yaml::Mapping::new();
+    entry.insert(Value::from("path"), Value::from(device));
+    entry.insert(Value::from(key), amount);
+    Ok(Value::Mapping(entry))
+}
+
 fn set_nested_map(map: &mut serde_yaml::Mapping, path: &[&str], value: IndexMap<String, Value>) {
     if path.is_empty() {
         return;
@@ -430,35 +906,157 @@ fn convert_mount_to_volume(mount_str: &str) -> String {
     }
 }
 
-fn parse_gpus(map: &mut serde_yaml::Mapping, value: &str) -> Result<(), String> {
-    let count_value = if value == "all" {
-        Value::String("all".to_string())
-    } else {
-        Value::Number(value.parse::<i64>()
-            .map_err(|_| format!("Invalid GPU count: {}", value))?.into())
-    };
-    
-    let mut device = IndexMap::new();
-    device.insert("driver".to_string(), Value::String("nvidia".to_string()));
-    device.insert("count".to_string(), count_value);
-    device.insert("capabilities".to_string(), Value::Sequence(vec![Value::String("gpu".to_string())]));
-    
-    let mut devices = IndexMap::new();
-    devices.insert("devices".to_string(), Value::Sequence(vec![
-        Value::Mapping(device.into_iter().map(|(k, v)| (Value::String(k), v)).collect())
-    ]));
-    
-    let mut reservations = IndexMap::new();
-    reservations.insert("reservations".to_string(), Value::Mapping(
-        devices.into_iter().map(|(k, v)| (Value::String(k), v)).collect()
-    ));
-    
-    let mut resources = IndexMap::new();
-    resources.insert("resources".to_string(), Value::Mapping(
-        reservations.into_iter().map(|(k, v)| (Value::String(k), v)).collect()
-    ));
-    
-    set_nested_map(map, &["deploy"], resources);
-    
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> ParsedCommand {
+        parse_command(input, &ParseOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_required_value_may_start_with_dash() {
+        let parsed = parse(r#"docker run -e -x --health-cmd "-f x" nginx"#);
+        assert_eq!(parsed.args["e"], vec!["-x"]);
+        assert_eq!(parsed.args["health-cmd"], vec!["-f x"]);
+        assert_eq!(parsed.image, "nginx");
+    }
+
+    #[test]
+    fn test_unknown_flag_keeps_image() {
+        let parsed = parse("docker run --some-new-flag value nginx");
+        assert_eq!(parsed.args["some-new-flag"], vec!["value"]);
+        assert_eq!(parsed.image, "nginx");
+        assert_eq!(parsed.warnings.len(), 1);
+
+        let parsed = parse("docker run --some-new-switch nginx");
+        assert_eq!(parsed.args["some-new-switch"], vec!["true"]);
+        assert_eq!(parsed.image, "nginx");
+
+        let parsed = parse("docker run --some-new-flag=value nginx");
+        assert_eq!(parsed.args["some-new-flag"], vec!["value"]);
+        assert_eq!(parsed.warnings, vec!["Unknown flag --some-new-flag with value 'value'"]);
+    }
+
+    #[test]
+    fn test_switch_values() {
+        let parsed = parse("docker run --privileged=false --init=1 -t=0 nginx");
+        assert_eq!(parsed.args["privileged"], vec!["false"]);
+        assert_eq!(parsed.args["init"], vec!["true"]);
+        assert_eq!(parsed.args["t"], vec!["false"]);
+
+        let result = parse_command("docker run --tty=maybe nginx", &ParseOptions::default());
+        assert!(result.unwrap_err().contains("Invalid boolean value"));
+    }
+
+    #[test]
+    fn test_short_flag_clusters() {
+        let parsed = parse("docker run -itdp8080:80 -e=A=b -a stdout nginx");
+        assert_eq!(parsed.args["i"], vec!["true"]);
+        assert_eq!(parsed.args["t"], vec!["true"]);
+        assert_eq!(parsed.args["d"], vec!["true"]);
+        assert_eq!(parsed.args["p"], vec!["8080:80"]);
+        assert_eq!(parsed.args["e"], vec!["A=b"]);
+        assert_eq!(parsed.args["a"], vec!["stdout"]);
+        assert_eq!(parsed.image, "nginx");
+    }
+
+    #[test]
+    fn test_missing_value() {
+        let result = parse_command("docker run nginx --name", &ParseOptions::default());
+        assert!(result.is_ok());
+        let result = parse_command("docker run --name", &ParseOptions::default());
+        assert!(result.unwrap_err().contains("requires a value"));
+    }
+
+    #[test]
+    fn test_end_of_options() {
+        let parsed = parse(r#"docker run --rm -a stdout alpine -- sh -c "echo hi""#);
+        assert_eq!(parsed.image, "alpine");
+        assert_eq!(parsed.command, vec!["sh", "-c", "echo hi"]);
+        assert_eq!(parsed.args["a"], vec!["stdout"]);
+
+        let parsed = parse("docker run -d -- alpine ls -la");
+        assert_eq!(parsed.image, "alpine");
+        assert_eq!(parsed.command, vec!["ls", "-la"]);
+    }
+
+    #[test]
+    fn test_command_flags_are_not_options() {
+        let parsed = parse("docker run alpine ls -la --color=auto -- x");
+        assert_eq!(parsed.image, "alpine");
+        assert_eq!(parsed.command, vec!["ls", "-la", "--color=auto", "--", "x"]);
+        assert!(parsed.args.is_empty());
+    }
+
+    #[test]
+    fn test_image_boundaries() {
+        let parsed = parse("docker run -v /a:/b -p 80:80 -it --name x nginx:1.25 nginx -g 'daemon off;'");
+        assert_eq!(parsed.image, "nginx:1.25");
+        assert_eq!(parsed.command, vec!["nginx", "-g", "daemon off;"]);
+
+        let parsed = parse("docker run -- -weird-image");
+        assert_eq!(parsed.image, "-weird-image");
+
+        let result = parse_command("docker run -d --", &ParseOptions::default());
+        assert!(result.unwrap_err().contains("No image specified"));
+    }
+
+    #[test]
+    fn test_command_value_styles() {
+        let args: Vec<String> = ["sh", "-c", "echo a b"].iter().map(|s| s.to_string()).collect();
+        let exec = command_value(&args, CommandStyle::Exec);
+        assert_eq!(serde_yaml::to_string(&exec).unwrap(), "- sh\n- -c\n- echo a b\n");
+        assert_eq!(command_value(&args, CommandStyle::Auto), exec);
+
+        let simple: Vec<String> = ["npm", "start"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(command_value(&simple, CommandStyle::Auto), Value::String("npm start".to_string()));
+        assert!(matches!(command_value(&simple, CommandStyle::Exec), Value::Sequence(_)));
+    }
+
+    #[test]
+    fn test_entrypoint_last_wins() {
+        let parsed = parse("docker run --entrypoint /bin/a --entrypoint '/bin/my tool' nginx");
+        let service = build_compose_value(&parsed.args, "default").unwrap();
+        let yaml = serde_yaml::to_string(&service).unwrap();
+        assert_eq!(yaml, "entrypoint:\n- /bin/my tool\n");
+    }
+
+    #[test]
+    fn test_network_long_syntax() {
+        let spec = parse_network_spec("name=backend,alias=api,alias=web,ip=10.0.0.5,driver-opt=com.docker.network.bridge.name=br0,gw-priority=10").unwrap();
+        assert_eq!(spec.name, "backend");
+        let expected: Value = serde_yaml::from_str(
+            "{aliases: [api, web], ipv4_address: 10.0.0.5, driver_opts: {com.docker.network.bridge.name: br0}, gw_priority: 10}",
+        )
+        .unwrap();
+        assert_eq!(Value::Mapping(spec.settings), expected);
+
+        assert_eq!(parse_network_spec("container:db").unwrap().name, "container:db");
+        assert!(parse_network_spec("alias=x").is_err());
+        assert!(parse_network_spec("name=a,colour=red").is_err());
+        assert!(parse_network_spec("name=a,priority=high").is_err());
+    }
+
+    #[test]
+    fn test_multiple_networks() {
+        let parsed = parse(
+            "docker run --network-alias main --ip 10.0.0.2 --network front \
+             --network name=back,ip=10.1.0.2,alias=db,mac-address=02:42:ac:11:00:02 nginx",
+        );
+        let network = primary_network(&parsed.args);
+        assert_eq!(network, "front");
+        let service = build_compose_value(&parsed.args, &network).unwrap();
+        let expected: Value = serde_yaml::from_str(
+            "networks:
+               front: {aliases: [main], ipv4_address: 10.0.0.2}
+               back: {ipv4_address: 10.1.0.2, aliases: [db], mac_address: '02:42:ac:11:00:02'}",
+        )
+        .unwrap();
+        assert_eq!(service, expected);
+
+        let parsed = parse("docker run --network host nginx");
+        let service = build_compose_value(&parsed.args, &primary_network(&parsed.args)).unwrap();
+        assert_eq!(service["network_mode"], Value::from("host"));
+    }
 }
@@ -0,0 +1,234 @@
+//! Serializers for the generated compose document.
+//!
+//! Conversion builds a single `serde_yaml::Value` document; the output format
+//! only decides which [`Serializer`] writes it out.
+
+use crate::options::OutputFormat;
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// Writes a compose document as text
+pub trait Serializer {
+    fn serialize(&self, document: &Value) -> Result<String, String>;
+}
+
+/// Block-style YAML with `indent` spaces per nesting level.
+///
+/// Sequences under a mapping key start at the key's column, as serde_yaml
+/// writes them, so indent 2 gives exactly serde_yaml's output.
+#[derive(Debug, Clone, Copy)]
+pub struct YamlSerializer {
+    pub indent: usize,
+}
+
+/// JSON, pretty-printed with `indent` spaces or compact when it is 0
+#[derive(Debug, Clone, Copy)]
+pub struct JsonSerializer {
+    pub indent: usize,
+}
+
+/// Returns the serializer for an output format
+pub fn serializer_for(format: OutputFormat, indent: usize) -> Box<dyn Serializer> {
+    match format {
+        OutputFormat::Yaml => Box::new(YamlSerializer { indent }),
+        OutputFormat::Json => Box::new(JsonSerializer { indent }),
+    }
+}
+
+impl Serializer for YamlSerializer {
+    fn serialize(&self, document: &Value) -> Result<String, String> {
+        // YAML needs at least one space per level
+        let indent = if self.indent == 0 { 2 } else { self.indent };
+        let mut out = String::new();
+        match document {
+            Value::Mapping(map) if !map.is_empty() => write_mapping(&mut out, map, 0, indent)?,
+            Value::Sequence(items) if !items.is_empty() => write_sequence(&mut out, items, 0, indent)?,
+            _ => {
+                let scalar = scalar(document, 0, indent)?;
+                out.push_str(&scalar);
+                out.push('\n');
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl Serializer for JsonSerializer {
+    fn serialize(&self, document: &Value) -> Result<String, String> {
+        let json_value: serde_json::Value = serde_yaml::from_value(document.clone())
+            .map_err(|e| format!("Failed to convert to JSON: {}", e))?;
+
+        if self.indent == 0 {
+            return serde_json::to_string(&json_value)
+                .map_err(|e| format!("Failed to serialize JSON: {}", e));
+        }
+
+        let indent = " ".repeat(self.indent);
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+        let mut buffer = Vec::new();
+        let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+        json_value
+            .serialize(&mut serializer)
+            .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
+        String::from_utf8(buffer).map_err(|e| format!("Failed to serialize JSON: {}", e))
+    }
+}
+
+/// Writes the entries of a non-empty mapping starting at column `col`
+fn write_mapping(out: &mut String, map: &serde_yaml::Mapping, col: usize, indent: usize) -> Result<(), String> {
+    for (i, (key, value)) in map.iter().enumerate() {
+        // The first entry of a sequence item follows its `- ` directly
+        if i > 0 || !out.ends_with("- ") {
+            out.push_str(&" ".repeat(col));
+        }
+        out.push_str(&scalar(key, col, indent)?);
+        out.push(':');
+        write_value(out, value, col, col + indent, indent)?;
+    }
+    Ok(())
+}
+
+/// Writes the items of a non-empty sequence with their dashes at column `col`
+fn write_sequence(out: &mut String, items: &[Value], col: usize, indent: usize) -> Result<(), String> {
+    for item in items {
+        if !out.ends_with("- ") {
+            out.push_str(&" ".repeat(col));
+        }
+        out.push('-');
+        write_value(out, item, col, col + 2, indent)?;
+    }
+    Ok(())
+}
+
+/// Writes the value after a `key:` or `-` at column `col`; nested mappings
+/// start at `child_col`, nested sequences at the parent's column
+fn write_value(out: &mut String, value: &Value, col: usize, child_col: usize, indent: usize) -> Result<(), String> {
+    let item = out.ends_with('-');
+    match value {
+        Value::Mapping(map) if !map.is_empty() => {
+            if item {
+                out.push(' ');
+            } else {
+                out.push('\n');
+            }
+            write_mapping(out, map, child_col, indent)
+        }
+        Value::Sequence(items) if !items.is_empty() => {
+            if item {
+                out.push(' ');
+                write_sequence(out, items, child_col, indent)
+            } else {
+                out.push('\n');
+                write_sequence(out, items, col, indent)
+            }
+        }
+        _ => {
+            out.push(' ');
+            out.push_str(&scalar(value, col, indent)?);
+            out.push('\n');
+            Ok(())
+        }
+    }
+}
+
+/// Renders a scalar (or empty collection) the way serde_yaml would, moving
+/// block scalar content to one level below column `col`
+fn scalar(value: &Value, col: usize, indent: usize) -> Result<String, String> {
+    let rendered = serde_yaml::to_string(value).map_err(|e| format!("Failed to serialize: {}", e))?;
+    let rendered = rendered.strip_suffix('\n').unwrap_or(&rendered);
+
+    let mut lines = rendered.lines();
+    let header = lines.next().unwrap_or_default();
+    if !header.starts_with('|') && !header.starts_with('>') {
+        return Ok(rendered.to_string());
+    }
+
+    // serde_yaml indents block content by 2 at the top level; an explicit
+    // indentation indicator is relative to the parent and must follow suit
+    let mut result: String = header
+        .chars()
+        .map(|c| if c.is_ascii_digit() { char::from_digit(indent.min(9) as u32, 10).unwrap_or(c) } else { c })
+        .collect();
+    let content_col = " ".repeat(col + indent);
+    for line in lines {
+        result.push('\n');
+        if !line.is_empty() {
+            result.push_str(&content_col);
+            result.push_str(line.get(2..).unwrap_or_default());
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r#"
+version: '3'
+services:
+  web:
+    image: nginx
+    command: [sh, -c, "echo hi"]
+    environment:
+      A: '1'
+      B: null
+    ports: ["80:80"]
+    healthcheck:
+      test: [CMD-SHELL, "curl -f http://localhost"]
+    script: "  indented\nsecond\n"
+    note: "line\nother"
+    nested:
+      - - a
+        - b
+      - {x: 1, y: [2, 3], z: {w: true}}
+      - {}
+    empty: []
+networks:
+  backend:
+    external: true
+volumes:
+  data: null
+"#;
+
+    fn document() -> Value {
+        serde_yaml::from_str(DOCUMENT).unwrap()
+    }
+
+    #[test]
+    fn test_yaml_indent_2_matches_serde_yaml() {
+        let document = document();
+        let yaml = YamlSerializer { indent: 2 }.serialize(&document).unwrap();
+        assert_eq!(yaml, serde_yaml::to_string(&document).unwrap());
+    }
+
+    #[test]
+    fn test_yaml_round_trips_at_any_indent() {
+        let document = document();
+        for indent in 1..=8 {
+            let yaml = YamlSerializer { indent }.serialize(&document).unwrap();
+            let parsed: Value = serde_yaml::from_str(&yaml).unwrap_or_else(|e| panic!("indent {}: {}\n{}", indent, e, yaml));
+            assert_eq!(parsed, document, "indent {}:\n{}", indent, yaml);
+        }
+    }
+
+    #[test]
+    fn test_yaml_indent_4() {
+        let document: Value = serde_yaml::from_str("services:\n  web:\n    ports: ['80:80']\n").unwrap();
+        let yaml = YamlSerializer { indent: 4 }.serialize(&document).unwrap();
+        assert_eq!(yaml, "services:\n    web:\n        ports:\n        - 80:80\n");
+    }
+
+    #[test]
+    fn test_json_indent() {
+        let document: Value = serde_yaml::from_str("services:\n  web:\n    image: nginx\n").unwrap();
+        assert_eq!(
+            JsonSerializer { indent: 4 }.serialize(&document).unwrap(),
+            "{\n    \"services\": {\n        \"web\": {\n            \"image\": \"nginx\"\n        }\n    }\n}"
+        );
+        assert_eq!(
+            JsonSerializer { indent: 0 }.serialize(&document).unwrap(),
+            r#"{"services":{"web":{"image":"nginx"}}}"#
+        );
+    }
+}
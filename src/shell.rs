@@ -0,0 +1,900 @@
+//! Shell-grammar tokenizer for docker commands.
+//!
+//! Splits a command line into words the same way the shell that would have
+//! run it does, so quoted whitespace, escapes and adjacent quoted segments
+//! (`--name="a"b`) come out exactly as docker would have received them.
+//! Parameter and command substitutions are not expanded here; they are kept
+//! as [`Segment::Expansion`] parts of a [`Word`] so the caller can decide how
+//! to render them (see [`crate::interpolation`]). Redirections end a command
+//! and are handed back separately in [`CommandLine::redirections`].
+
+use serde::{Deserialize, Serialize};
+
+/// Quoting rules used when splitting a command line into words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShellDialect {
+    /// POSIX `sh`/`bash`: `'...'`, `"..."`, `$'...'`, backslash escapes, `#` comments
+    #[default]
+    #[serde(alias = "sh", alias = "bash")]
+    Posix,
+    /// PowerShell: `'...'` with `''`, `"..."` with backtick escapes and `""`
+    #[serde(alias = "pwsh")]
+    PowerShell,
+    /// Windows `cmd.exe`: `"..."` quoting and `^` escapes
+    Cmd,
+}
+
+/// A lexical token of a command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// A word after quote removal
+    Word(Word),
+    /// An unquoted control operator (`;`, `&`, `&&`, `|`, `||`)
+    Operator(String),
+    /// An unquoted redirection operator (`>`, `>>`, `<`, `2>`, `2>&1`, ...)
+    Redirection(String),
+}
+
+/// The words of one command.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CommandLine {
+    pub words: Vec<Word>,
+    /// Everything from the first redirection on, one entry per operator,
+    /// e.g. `["2>&1", "> out.log"]`
+    pub redirections: Vec<String>,
+}
+
+impl CommandLine {
+    fn push(&mut self, token: &Token) {
+        match (token, self.redirections.last_mut()) {
+            (Token::Redirection(op), _) => self.redirections.push(op.clone()),
+            (Token::Word(word), None) => self.words.push(word.clone()),
+            // Targets and any words after them go with the redirection
+            (Token::Word(word), Some(last)) => {
+                last.push(' ');
+                last.push_str(&word.raw());
+            }
+            (Token::Operator(_), _) => {}
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.redirections.is_empty()
+    }
+}
+
+/// A shell word made of literal text and unexpanded substitutions.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Word {
+    pub segments: Vec<Segment>,
+}
+
+/// Part of a [`Word`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Text taken literally, after quote and escape removal
+    Literal(String),
+    /// A substitution exactly as written, e.g. `$HOME`, `${PWD}`, `$(pwd)` or `%CD%`
+    Expansion(String),
+}
+
+impl Word {
+    /// Returns the word with substitutions left as written.
+    pub fn raw(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) | Segment::Expansion(text) => text.as_str(),
+            })
+            .collect()
+    }
+
+    /// Returns true if the word contains a substitution.
+    pub fn has_expansion(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Expansion(_)))
+    }
+}
+
+/// Splits a POSIX shell command into words.
+pub fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    tokenize_with(input, ShellDialect::Posix)
+}
+
+/// Splits a command into words using the given shell dialect.
+///
+/// Newlines are treated as plain whitespace so that commands pasted over
+/// several lines still form a single command. Control operators are rejected
+/// because only one command can be converted at a time, and redirections are
+/// dropped.
+pub fn tokenize_with(input: &str, dialect: ShellDialect) -> Result<Vec<String>, String> {
+    Ok(split_words(input, dialect)?.iter().map(Word::raw).collect())
+}
+
+/// Splits a script into commands at `;`, `&&`, `||` and `&`, and at line
+/// breaks that are followed by a new docker command. A command wrapped over
+/// several lines without continuation characters therefore stays whole.
+pub fn split_commands(input: &str, dialect: ShellDialect) -> Result<Vec<CommandLine>, String> {
+    let tokens = Lexer::new(input, true).run(dialect)?;
+    let mut commands = Vec::new();
+    let mut current = CommandLine::default();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let split = match token {
+            Token::Operator(op) if op == "|" => {
+                return Err("Unexpected shell operator '|': pipelines cannot be converted".to_string())
+            }
+            Token::Operator(op) if op == "\n" => starts_command(&tokens[i + 1..]),
+            Token::Operator(_) => true,
+            _ => {
+                current.push(token);
+                false
+            }
+        };
+        if split && !current.is_empty() {
+            commands.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        commands.push(current);
+    }
+    Ok(commands)
+}
+
+/// Returns true if the line starting at `tokens` runs a container runtime
+fn starts_command(tokens: &[Token]) -> bool {
+    let line: Vec<String> = tokens
+        .iter()
+        .map_while(|token| match token {
+            Token::Word(word) => Some(word.raw()),
+            Token::Operator(_) | Token::Redirection(_) => None,
+        })
+        .collect();
+    line.is_empty() || crate::invocation::starts_with_runtime(&line)
+}
+
+/// Splits a single command into [`Word`]s, keeping substitutions separate.
+/// Redirections are dropped, see [`split_command`].
+pub fn split_words(input: &str, dialect: ShellDialect) -> Result<Vec<Word>, String> {
+    Ok(split_command(input, dialect)?.words)
+}
+
+/// Splits a single command into its words and trailing redirections.
+pub fn split_command(input: &str, dialect: ShellDialect) -> Result<CommandLine, String> {
+    let mut command = CommandLine::default();
+    for token in lex(input, dialect)? {
+        if let Token::Operator(op) = token {
+            return Err(format!(
+                "Unexpected shell operator '{}': only a single command can be converted",
+                op
+            ));
+        }
+        command.push(&token);
+    }
+    Ok(command)
+}
+
+/// Splits a command line into words and control operators.
+pub fn lex(input: &str, dialect: ShellDialect) -> Result<Vec<Token>, String> {
+    Lexer::new(input, false).run(dialect)
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    tokens: Vec<Token>,
+    current: String,
+    segments: Vec<Segment>,
+    // Distinguishes an empty quoted word (`""`) from no word at all
+    in_word: bool,
+    /// Emit unquoted line breaks as `\n` operators instead of whitespace
+    newlines: bool,
+}
+
+impl Lexer {
+    fn new(input: &str, newlines: bool) -> Self {
+        Self {
+            chars: input.chars().collect(),
+            pos: 0,
+            tokens: Vec::new(),
+            current: String::new(),
+            segments: Vec::new(),
+            in_word: false,
+            newlines,
+        }
+    }
+
+    fn run(mut self, dialect: ShellDialect) -> Result<Vec<Token>, String> {
+        match dialect {
+            ShellDialect::Posix => self.lex_posix()?,
+            ShellDialect::PowerShell => self.lex_powershell()?,
+            ShellDialect::Cmd => self.lex_cmd()?,
+        }
+
+        self.finish_word();
+        Ok(self.tokens)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek();
+        if ch.is_some() {
+            self.pos += 1;
+        }
+        ch
+    }
+
+    fn push(&mut self, ch: char) {
+        self.current.push(ch);
+        self.in_word = true;
+    }
+
+    fn push_expansion(&mut self, raw: String) {
+        if !self.current.is_empty() {
+            let literal = std::mem::take(&mut self.current);
+            self.segments.push(Segment::Literal(literal));
+        }
+        self.segments.push(Segment::Expansion(raw));
+        self.in_word = true;
+    }
+
+    fn finish_word(&mut self) {
+        if self.in_word {
+            if !self.current.is_empty() || self.segments.is_empty() {
+                let literal = std::mem::take(&mut self.current);
+                self.segments.push(Segment::Literal(literal));
+            }
+            let segments = std::mem::take(&mut self.segments);
+            self.tokens.push(Token::Word(Word { segments }));
+            self.in_word = false;
+        }
+    }
+
+    /// Reads until the bracket matching `open` (already consumed), honouring
+    /// nesting and quotes, and returns the text including the closing bracket.
+    fn read_balanced(&mut self, open: char, close: char) -> Result<String, String> {
+        let mut text = String::new();
+        let mut depth = 1;
+        let mut quote: Option<char> = None;
+        while let Some(ch) = self.next() {
+            text.push(ch);
+            match quote {
+                Some('"') if ch == '\\' => {
+                    if let Some(escaped) = self.next() {
+                        text.push(escaped);
+                    }
+                }
+                Some(q) if ch == q => quote = None,
+                Some(_) => {}
+                None if ch == '\'' || ch == '"' => quote = Some(ch),
+                None if ch == '\\' => {
+                    if let Some(escaped) = self.next() {
+                        text.push(escaped);
+                    }
+                }
+                None if ch == open => depth += 1,
+                None if ch == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(text);
+                    }
+                }
+                None => {}
+            }
+        }
+        Err(format!("Unterminated '{}' in substitution", open))
+    }
+
+    /// Reads a POSIX substitution after its `$`, or returns `None` for a lone `$`.
+    fn posix_expansion(&mut self) -> Result<Option<String>, String> {
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                Ok(Some(format!("${{{}", self.read_balanced('{', '}')?)))
+            }
+            Some('(') => {
+                self.pos += 1;
+                Ok(Some(format!("$({}", self.read_balanced('(', ')')?)))
+            }
+            Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+                let mut raw = "$".to_string();
+                while let Some(c) = self.peek() {
+                    if c != '_' && !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    raw.push(c);
+                    self.pos += 1;
+                }
+                Ok(Some(raw))
+            }
+            Some(c) if c.is_ascii_digit() || "@*#?$!-".contains(c) => {
+                self.pos += 1;
+                Ok(Some(format!("${}", c)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads a PowerShell variable or subexpression after its `$`.
+    fn powershell_expansion(&mut self) -> Result<Option<String>, String> {
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                Ok(Some(format!("${{{}", self.read_balanced('{', '}')?)))
+            }
+            Some('(') => {
+                self.pos += 1;
+                Ok(Some(format!("$({}", self.read_balanced('(', ')')?)))
+            }
+            Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+                let mut raw = "$".to_string();
+                while let Some(c) = self.peek() {
+                    // `$env:NAME` is a single variable reference
+                    let scope = c == ':' && raw.eq_ignore_ascii_case("$env");
+                    if !scope && c != '_' && !c.is_ascii_alphanumeric() {
+                        break;
+                    }
+                    raw.push(c);
+                    self.pos += 1;
+                }
+                Ok(Some(raw))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads a `%NAME%` reference after its first `%`, if one follows.
+    fn cmd_expansion(&mut self) -> Option<String> {
+        let rest = &self.chars[self.pos..];
+        let end = rest.iter().position(|&c| c == '%')?;
+        let name: String = rest[..end].iter().collect();
+        if name.is_empty() || !name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        self.pos += end + 1;
+        Some(format!("%{}%", name))
+    }
+
+    /// Reads a backquoted command substitution after its opening backquote.
+    fn backquoted(&mut self) -> Result<String, String> {
+        let mut raw = "`".to_string();
+        while let Some(ch) = self.next() {
+            raw.push(ch);
+            match ch {
+                '`' => return Ok(raw),
+                '\\' => {
+                    if let Some(escaped) = self.next() {
+                        raw.push(escaped);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err("Unterminated backquote".to_string())
+    }
+
+    /// Reads a redirection operator starting with `ch`, which was already
+    /// consumed. A file descriptor number right before it belongs to it.
+    fn redirection(&mut self, ch: char) {
+        let fd = self.segments.is_empty()
+            && !self.current.is_empty()
+            && self.current.chars().all(|c| c.is_ascii_digit());
+        let mut op = if fd { std::mem::take(&mut self.current) } else { String::new() };
+        if fd {
+            self.in_word = false;
+        }
+        self.finish_word();
+        op.push(ch);
+        // `&>` redirects both output streams
+        if ch == '&' {
+            op.push(self.next().unwrap_or_default());
+        }
+        if matches!((op.ends_with('>'), self.peek()), (true, Some('>' | '|')) | (false, Some('>'))) {
+            op.push(self.next().unwrap_or_default());
+        }
+        // `>&1`, `<&0` and `>&-` duplicate or close a descriptor
+        if self.peek() == Some('&') {
+            self.pos += 1;
+            op.push('&');
+            while let Some(c) = self.peek().filter(|c| c.is_ascii_digit() || *c == '-') {
+                self.pos += 1;
+                op.push(c);
+            }
+        }
+        self.tokens.push(Token::Redirection(op));
+    }
+
+    /// Consumes a line break (`\n` or `\r\n`) if one follows.
+    fn skip_newline(&mut self) -> bool {
+        match (self.peek(), self.peek_at(1)) {
+            (Some('\n'), _) => {
+                self.pos += 1;
+                true
+            }
+            (Some('\r'), Some('\n')) => {
+                self.pos += 2;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        while let Some(ch) = self.peek() {
+            if ch == '\n' {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Reads a control operator starting with `ch`, which was already consumed.
+    fn operator(&mut self, ch: char) {
+        self.finish_word();
+        let mut op = ch.to_string();
+        if (ch == '&' || ch == '|') && self.peek() == Some(ch) {
+            self.pos += 1;
+            op.push(ch);
+        }
+        self.tokens.push(Token::Operator(op));
+    }
+
+    fn lex_posix(&mut self) -> Result<(), String> {
+        while let Some(ch) = self.next() {
+            match ch {
+                '\\' => {
+                    if !self.skip_newline() {
+                        match self.next() {
+                            Some(escaped) => self.push(escaped),
+                            None => self.push('\\'),
+                        }
+                    }
+                }
+                '\'' => {
+                    self.in_word = true;
+                    loop {
+                        match self.next() {
+                            Some('\'') => break,
+                            Some(c) => self.current.push(c),
+                            None => return Err("Unterminated single quote".to_string()),
+                        }
+                    }
+                }
+                '"' => {
+                    self.in_word = true;
+                    self.posix_double_quoted()?;
+                }
+                '$' if self.peek() == Some('\'') => {
+                    self.pos += 1;
+                    self.in_word = true;
+                    self.ansi_c_quoted()?;
+                }
+                '$' => match self.posix_expansion()? {
+                    Some(raw) => self.push_expansion(raw),
+                    None => self.push('$'),
+                },
+                '`' => {
+                    let raw = self.backquoted()?;
+                    self.push_expansion(raw);
+                }
+                '#' if !self.in_word => self.skip_comment(),
+                '&' if self.peek() == Some('>') => self.redirection(ch),
+                ';' | '&' | '|' => self.operator(ch),
+                '>' | '<' => self.redirection(ch),
+                '\n' if self.newlines => self.operator('\n'),
+                c if c.is_whitespace() => self.finish_word(),
+                c => self.push(c),
+            }
+        }
+        Ok(())
+    }
+
+    fn posix_double_quoted(&mut self) -> Result<(), String> {
+        loop {
+            match self.next() {
+                Some('"') => return Ok(()),
+                Some('\\') => {
+                    if self.skip_newline() {
+                        continue;
+                    }
+                    match self.peek() {
+                        // Inside double quotes backslash only escapes these
+                        Some(c @ ('$' | '`' | '"' | '\\')) => {
+                            self.pos += 1;
+                            self.current.push(c);
+                        }
+                        _ => self.current.push('\\'),
+                    }
+                }
+                Some('$') => match self.posix_expansion()? {
+                    Some(raw) => self.push_expansion(raw),
+                    None => self.current.push('$'),
+                },
+                Some('`') => {
+                    let raw = self.backquoted()?;
+                    self.push_expansion(raw);
+                }
+                Some(c) => self.current.push(c),
+                None => return Err("Unterminated double quote".to_string()),
+            }
+        }
+    }
+
+    fn ansi_c_quoted(&mut self) -> Result<(), String> {
+        loop {
+            let ch = match self.next() {
+                Some('\'') => return Ok(()),
+                Some(c) => c,
+                None => return Err("Unterminated $'...' string".to_string()),
+            };
+            if ch != '\\' {
+                self.current.push(ch);
+                continue;
+            }
+            let escaped = match self.next() {
+                Some(c) => c,
+                None => return Err("Unterminated $'...' string".to_string()),
+            };
+            match escaped {
+                'n' => self.current.push('\n'),
+                't' => self.current.push('\t'),
+                'r' => self.current.push('\r'),
+                'a' => self.current.push('\x07'),
+                'b' => self.current.push('\x08'),
+                'e' | 'E' => self.current.push('\x1b'),
+                'f' => self.current.push('\x0c'),
+                'v' => self.current.push('\x0b'),
+                'x' => {
+                    let code = self.hex_digits(2);
+                    self.push_code(code, "\\x")?;
+                }
+                'u' => {
+                    let code = self.hex_digits(4);
+                    self.push_code(code, "\\u")?;
+                }
+                'U' => {
+                    let code = self.hex_digits(8);
+                    self.push_code(code, "\\U")?;
+                }
+                '0'..='7' => {
+                    let mut code = escaped.to_digit(8).unwrap_or(0);
+                    for _ in 0..2 {
+                        match self.peek().and_then(|c| c.to_digit(8)) {
+                            Some(digit) => {
+                                self.pos += 1;
+                                code = code * 8 + digit;
+                            }
+                            None => break,
+                        }
+                    }
+                    self.push_code(Some(code), "\\0")?;
+                }
+                // \\, \', \" and \? stand for themselves
+                '\\' | '\'' | '"' | '?' => self.current.push(escaped),
+                other => {
+                    self.current.push('\\');
+                    self.current.push(other);
+                }
+            }
+        }
+    }
+
+    fn hex_digits(&mut self, max: usize) -> Option<u32> {
+        let mut code = None;
+        for _ in 0..max {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => {
+                    self.pos += 1;
+                    code = Some(code.unwrap_or(0) * 16 + digit);
+                }
+                None => break,
+            }
+        }
+        code
+    }
+
+    fn push_code(&mut self, code: Option<u32>, escape: &str) -> Result<(), String> {
+        match code {
+            Some(code) => {
+                let ch = char::from_u32(code)
+                    .ok_or_else(|| format!("Invalid escape {} in $'...' string", escape))?;
+                self.current.push(ch);
+            }
+            None => self.current.push_str(escape),
+        }
+        Ok(())
+    }
+
+    fn lex_powershell(&mut self) -> Result<(), String> {
+        while let Some(ch) = self.next() {
+            match ch {
+                '`' => {
+                    if !self.skip_newline() {
+                        match self.next() {
+                            Some(escaped) => self.push(powershell_escape(escaped)),
+                            None => self.push('`'),
+                        }
+                    }
+                }
+                '\'' => {
+                    self.in_word = true;
+                    loop {
+                        match self.next() {
+                            Some('\'') if self.peek() == Some('\'') => {
+                                self.pos += 1;
+                                self.current.push('\'');
+                            }
+                            Some('\'') => break,
+                            Some(c) => self.current.push(c),
+                            None => return Err("Unterminated single quote".to_string()),
+                        }
+                    }
+                }
+                '"' => {
+                    self.in_word = true;
+                    loop {
+                        match self.next() {
+                            Some('"') if self.peek() == Some('"') => {
+                                self.pos += 1;
+                                self.current.push('"');
+                            }
+                            Some('"') => break,
+                            Some('`') => match self.next() {
+                                Some(escaped) => self.current.push(powershell_escape(escaped)),
+                                None => return Err("Unterminated double quote".to_string()),
+                            },
+                            Some('$') => match self.powershell_expansion()? {
+                                Some(raw) => self.push_expansion(raw),
+                                None => self.current.push('$'),
+                            },
+                            Some(c) => self.current.push(c),
+                            None => return Err("Unterminated double quote".to_string()),
+                        }
+                    }
+                }
+                '$' => match self.powershell_expansion()? {
+                    Some(raw) => self.push_expansion(raw),
+                    None => self.push('$'),
+                },
+                '#' if !self.in_word => self.skip_comment(),
+                ';' | '|' => self.operator(ch),
+                '&' if self.peek() == Some('&') => self.operator(ch),
+                '\n' if self.newlines => self.operator('\n'),
+                c if c.is_whitespace() => self.finish_word(),
+                c => self.push(c),
+            }
+        }
+        Ok(())
+    }
+
+    fn lex_cmd(&mut self) -> Result<(), String> {
+        while let Some(ch) = self.next() {
+            match ch {
+                '^' => {
+                    if !self.skip_newline() {
+                        match self.next() {
+                            Some(escaped) => self.push(escaped),
+                            None => self.push('^'),
+                        }
+                    }
+                }
+                '"' => {
+                    self.in_word = true;
+                    loop {
+                        match self.next() {
+                            Some('"') if self.peek() == Some('"') => {
+                                self.pos += 1;
+                                self.current.push('"');
+                            }
+                            Some('"') => break,
+                            Some('\\') if self.peek() == Some('"') => {
+                                self.pos += 1;
+                                self.current.push('"');
+                            }
+                            Some('%') => match self.cmd_expansion() {
+                                Some(raw) => self.push_expansion(raw),
+                                None => self.current.push('%'),
+                            },
+                            Some(c) => self.current.push(c),
+                            None => return Err("Unterminated double quote".to_string()),
+                        }
+                    }
+                }
+                '%' => match self.cmd_expansion() {
+                    Some(raw) => self.push_expansion(raw),
+                    None => self.push('%'),
+                },
+                '&' | '|' => self.operator(ch),
+                '\n' if self.newlines => self.operator('\n'),
+                c if c.is_whitespace() => self.finish_word(),
+                c => self.push(c),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn powershell_escape(ch: char) -> char {
+    match ch {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        'a' => '\x07',
+        'b' => '\x08',
+        'e' => '\x1b',
+        'f' => '\x0c',
+        'v' => '\x0b',
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(input: &str) -> Vec<String> {
+        tokenize(input).unwrap()
+    }
+
+    #[test]
+    fn test_posix_corpus() {
+        let corpus: &[(&str, &[&str])] = &[
+            ("docker run nginx", &["docker", "run", "nginx"]),
+            ("  docker\trun   nginx  ", &["docker", "run", "nginx"]),
+            (r#"-e "A=  b" nginx"#, &["-e", "A=  b", "nginx"]),
+            ("-e 'A=  b' nginx", &["-e", "A=  b", "nginx"]),
+            (r#"--name="a"b"#, &["--name=ab"]),
+            (r#"--name='a'"b"c"#, &["--name=abc"]),
+            (r#"'a\b'"#, &[r"a\b"]),
+            (r#""a\b""#, &[r"a\b"]),
+            (r#""a\"b""#, &[r#"a"b"#]),
+            (r#""a\$b""#, &["a$b"]),
+            (r"a\ b", &["a b"]),
+            (r"a\\b", &[r"a\b"]),
+            ("$'a\\nb'", &["a\nb"]),
+            ("$'it\\'s'", &["it's"]),
+            ("$'\\x41\\101\\u00e9'", &["AAé"]),
+            ("\"\" nginx", &["", "nginx"]),
+            ("docker run \\\n  -p 80:80 \\\r\n  nginx", &["docker", "run", "-p", "80:80", "nginx"]),
+            ("docker run\n-p 80:80\nnginx", &["docker", "run", "-p", "80:80", "nginx"]),
+            ("docker run nginx # trailing comment", &["docker", "run", "nginx"]),
+            ("-l a#b nginx", &["-l", "a#b", "nginx"]),
+            ("\"line\nbreak\"", &["line\nbreak"]),
+            ("--health-cmd 'curl -f http://x || exit 1'", &["--health-cmd", "curl -f http://x || exit 1"]),
+            ("-l rule=Host(`a.com`)", &["-l", "rule=Host(`a.com`)"]),
+            ("-v `pwd`:/x nginx", &["-v", "`pwd`:/x", "nginx"]),
+            ("-e \"A=`echo \\`x\\``\" nginx", &["-e", "A=`echo \\`x\\``", "nginx"]),
+            ("docker run nginx > out.log", &["docker", "run", "nginx"]),
+            ("docker run nginx 2>&1 >>out.log", &["docker", "run", "nginx"]),
+            ("-e 'a>b' -e a\\<b nginx", &["-e", "a>b", "-e", "a<b", "nginx"]),
+        ];
+
+        for (input, expected) in corpus {
+            assert_eq!(&words(input), expected, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_posix_operators() {
+        let tokens = lex("docker run a && docker run b; echo x | y", ShellDialect::Posix).unwrap();
+        let ops: Vec<&Token> = tokens
+            .iter()
+            .filter(|t| matches!(t, Token::Operator(_)))
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                &Token::Operator("&&".to_string()),
+                &Token::Operator(";".to_string()),
+                &Token::Operator("|".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redirections_end_the_command() {
+        let command = split_command("docker run nginx 2>&1 > out.log x &> all.log <in", ShellDialect::Posix).unwrap();
+        let words: Vec<String> = command.words.iter().map(Word::raw).collect();
+        assert_eq!(words, vec!["docker", "run", "nginx"]);
+        assert_eq!(command.redirections, vec!["2>&1", "> out.log x", "&> all.log", "< in"]);
+
+        let commands = split_commands("docker run a > a.log; docker run b", ShellDialect::Posix).unwrap();
+        assert_eq!(commands[0].redirections, vec!["> a.log"]);
+        assert!(commands[1].redirections.is_empty());
+        assert!(tokenize("-v `pwd:/x").unwrap_err().contains("Unterminated backquote"));
+    }
+
+    #[test]
+    fn test_operator_rejected() {
+        let result = tokenize("docker run nginx && echo done");
+        assert!(result.unwrap_err().contains("'&&'"));
+    }
+
+    #[test]
+    fn test_split_commands() {
+        let commands = |input: &str, dialect| -> Vec<Vec<String>> {
+            split_commands(input, dialect)
+                .unwrap()
+                .iter()
+                .map(|command| command.words.iter().map(Word::raw).collect())
+                .collect()
+        };
+
+        let script = "docker run a && docker run b; docker run\n  -p 80:80 c\n\nsudo -E docker run d";
+        assert_eq!(
+            commands(script, ShellDialect::Posix),
+            vec![
+                vec!["docker", "run", "a"],
+                vec!["docker", "run", "b"],
+                vec!["docker", "run", "-p", "80:80", "c"],
+                vec!["sudo", "-E", "docker", "run", "d"],
+            ]
+        );
+        assert_eq!(
+            commands("docker run a\r\npodman run b", ShellDialect::PowerShell),
+            vec![vec!["docker", "run", "a"], vec!["podman", "run", "b"]]
+        );
+        assert!(split_commands("docker run a | tee x", ShellDialect::Posix).is_err());
+    }
+
+    #[test]
+    fn test_unterminated_quotes() {
+        assert!(tokenize("-e 'A=b").is_err());
+        assert!(tokenize("-e \"A=b").is_err());
+        assert!(tokenize("$'abc").is_err());
+    }
+
+    #[test]
+    fn test_powershell_corpus() {
+        let corpus: &[(&str, &[&str])] = &[
+            ("docker run nginx", &["docker", "run", "nginx"]),
+            ("-e 'it''s'", &["-e", "it's"]),
+            (r#"-e "say ""hi""""#, &["-e", r#"say "hi""#]),
+            ("-e \"a`tb\"", &["-e", "a\tb"]),
+            (r"-v C:\data:/data", &["-v", r"C:\data:/data"]),
+            ("docker run `\r\n  nginx", &["docker", "run", "nginx"]),
+            ("-l a&b", &["-l", "a&b"]),
+        ];
+
+        for (input, expected) in corpus {
+            assert_eq!(
+                &tokenize_with(input, ShellDialect::PowerShell).unwrap(),
+                expected,
+                "input: {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_cmd_corpus() {
+        let corpus: &[(&str, &[&str])] = &[
+            ("docker run nginx", &["docker", "run", "nginx"]),
+            (r#"-e "A=  b""#, &["-e", "A=  b"]),
+            (r#"-e "say \"hi\"""#, &["-e", r#"say "hi""#]),
+            ("-e A^&B", &["-e", "A&B"]),
+            ("docker run ^\r\n  nginx", &["docker", "run", "nginx"]),
+            (r"-v C:\data:/data", &["-v", r"C:\data:/data"]),
+            ("-e 'x'", &["-e", "'x'"]),
+        ];
+
+        for (input, expected) in corpus {
+            assert_eq!(
+                &tokenize_with(input, ShellDialect::Cmd).unwrap(),
+                expected,
+                "input: {:?}",
+                input
+            );
+        }
+    }
+}
@@ -0,0 +1,514 @@
+//! References between the services of a multi-command stack.
+//!
+//! When several commands are converted together, flags that point at another
+//! container by its `--name` are rewritten to point at the service converted
+//! from that container:
+//!
+//! - `--network`, `--pid` and `--ipc` `container:db` become `service:db`
+//! - `--volumes-from db[:ro]` becomes `db[:ro]`
+//! - `--link db:database` is dropped in favour of a `database` network alias
+//!   on `db`
+//!
+//! Every resolved reference is a dependency. [`infer_dependencies`] adds the
+//! services whose hostname appears in another service's environment, and
+//! [`add_depends_on`] writes them all out, waiting for a healthy target where
+//! it has a healthcheck.
+
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+
+/// Container names (`--name`) of a stack, mapped to their service names
+pub type ContainerNames = IndexMap<String, String>;
+
+/// When a dependent service may start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    #[default]
+    ServiceStarted,
+    ServiceHealthy,
+}
+
+/// A `depends_on` edge and why it was added
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dependency {
+    pub service: String,
+    pub target: String,
+    pub condition: Condition,
+    /// E.g. `--link pg:database` or `environment DB_HOST=db`
+    pub reasons: Vec<String>,
+}
+
+impl Dependency {
+    fn new(service: &str, target: &str, reason: String) -> Self {
+        Self {
+            service: service.to_string(),
+            target: target.to_string(),
+            condition: Condition::default(),
+            reasons: vec![reason],
+        }
+    }
+}
+
+/// Rewrites the container references of every service in `services`,
+/// returning the dependencies they imply
+pub fn resolve_references(
+    services: &mut Mapping,
+    containers: &ContainerNames,
+    warnings: &mut Vec<String>,
+) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+    // Link aliases as (target service, alias), added once every service is done
+    let mut aliases = Vec::new();
+
+    for (name, service) in services.iter_mut() {
+        let (Some(name), Value::Mapping(service)) = (name.as_str(), service) else {
+            continue;
+        };
+
+        let mut references = Vec::new();
+        for key in ["network_mode", "pid", "ipc"] {
+            resolve_mode(service, key, containers, &mut references);
+        }
+        resolve_volumes_from(service, containers, &mut references);
+        resolve_links(service, containers, &mut references, &mut aliases, warnings);
+
+        dependencies.extend(
+            references
+                .into_iter()
+                .filter(|(target, _)| target != name)
+                .map(|(target, reason)| Dependency::new(name, &target, reason)),
+        );
+    }
+
+    for (target, alias) in aliases {
+        add_alias(services, &target, &alias, warnings);
+    }
+    dependencies
+}
+
+/// Finds services whose environment names another service as a host, by its
+/// service name, container name, hostname or a network alias
+pub fn infer_dependencies(services: &Mapping) -> Vec<Dependency> {
+    let hosts = hostnames(services);
+    let mut dependencies = Vec::new();
+
+    for (name, service) in services {
+        let Some(name) = name.as_str() else {
+            continue;
+        };
+        for (key, value) in environment(service) {
+            for host in referenced_hosts(&value) {
+                let Some(target) = hosts.get(&host.to_ascii_lowercase()) else {
+                    continue;
+                };
+                if target != name {
+                    dependencies.push(Dependency::new(name, target, format!("environment {}={}", key, value)));
+                }
+            }
+        }
+    }
+    dependencies
+}
+
+/// Writes `dependencies` to `depends_on`, merging duplicate edges and
+/// setting each one's condition. Edges that would close a cycle are dropped
+/// with a warning; the rest are returned.
+pub fn add_depends_on(
+    services: &mut Mapping,
+    dependencies: Vec<Dependency>,
+    warnings: &mut Vec<String>,
+) -> Vec<Dependency> {
+    let mut edges: Vec<Dependency> = Vec::new();
+    for mut dependency in dependencies {
+        if let Some(edge) = edges
+            .iter_mut()
+            .find(|edge| edge.service == dependency.service && edge.target == dependency.target)
+        {
+            for reason in dependency.reasons {
+                if !edge.reasons.contains(&reason) {
+                    edge.reasons.push(reason);
+                }
+            }
+            continue;
+        }
+        if depends_on(&edges, &dependency.target, &dependency.service) {
+            warnings.push(format!(
+                "Not adding depends_on from '{}' to '{}': '{}' already depends on '{}'",
+                dependency.service, dependency.target, dependency.target, dependency.service
+            ));
+            continue;
+        }
+        if services.get(dependency.target.as_str()).is_some_and(has_healthcheck) {
+            dependency.condition = Condition::ServiceHealthy;
+        }
+        edges.push(dependency);
+    }
+
+    for (name, service) in services.iter_mut() {
+        let (Some(name), Value::Mapping(service)) = (name.as_str(), service) else {
+            continue;
+        };
+        let targets: Vec<&Dependency> = edges.iter().filter(|edge| edge.service == name).collect();
+        if targets.is_empty() {
+            continue;
+        }
+
+        // Conditions need the long syntax; plain starts keep the short one
+        let depends_on = if targets.iter().all(|edge| edge.condition == Condition::ServiceStarted) {
+            Value::Sequence(targets.iter().map(|edge| Value::from(edge.target.as_str())).collect())
+        } else {
+            Value::Mapping(
+                targets
+                    .iter()
+                    .map(|edge| {
+                        let condition = serde_yaml::to_value(edge.condition).unwrap_or_default();
+                        let entry = Mapping::from_iter([(Value::from("condition"), condition)]);
+                        (Value::from(edge.target.as_str()), Value::Mapping(entry))
+                    })
+                    .collect(),
+            )
+        };
+        service.insert(Value::from("depends_on"), depends_on);
+    }
+    edges
+}
+
+/// Returns true if `from` reaches `to` through `edges`
+fn depends_on(edges: &[Dependency], from: &str, to: &str) -> bool {
+    let mut pending = vec![from];
+    let mut seen = Vec::new();
+    while let Some(service) = pending.pop() {
+        if service == to {
+            return true;
+        }
+        if seen.contains(&service) {
+            continue;
+        }
+        seen.push(service);
+        pending.extend(edges.iter().filter(|edge| edge.service == service).map(|edge| edge.target.as_str()));
+    }
+    false
+}
+
+fn has_healthcheck(service: &Value) -> bool {
+    let Some(healthcheck) = service.get("healthcheck") else {
+        return false;
+    };
+    let disabled = healthcheck.get("disable").and_then(Value::as_bool) == Some(true)
+        || healthcheck.get("test").and_then(|test| test.get(0)).and_then(Value::as_str) == Some("NONE");
+    healthcheck.get("test").is_some() && !disabled
+}
+
+/// Lowercased names each service can be reached by
+fn hostnames(services: &Mapping) -> IndexMap<String, String> {
+    let mut hosts = IndexMap::new();
+    for (name, service) in services {
+        let Some(name) = name.as_str() else {
+            continue;
+        };
+        let mut names = vec![name];
+        names.extend(["container_name", "hostname"].iter().filter_map(|key| service.get(key)?.as_str()));
+        if let Some(Value::Mapping(networks)) = service.get("networks") {
+            for attachment in networks.values() {
+                if let Some(Value::Sequence(aliases)) = attachment.get("aliases") {
+                    names.extend(aliases.iter().filter_map(Value::as_str));
+                }
+            }
+        }
+        for host in names {
+            hosts.entry(host.to_ascii_lowercase()).or_insert_with(|| name.to_string());
+        }
+    }
+    hosts
+}
+
+/// `(KEY, value)` pairs of a list- or map-style `environment`
+fn environment(service: &Value) -> Vec<(String, String)> {
+    match service.get("environment") {
+        Some(Value::Sequence(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str()?.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        Some(Value::Mapping(entries)) => entries
+            .iter()
+            .filter_map(|(key, value)| {
+                let value = match value {
+                    Value::String(value) => value.clone(),
+                    Value::Number(number) => number.to_string(),
+                    _ => return None,
+                };
+                Some((key.as_str()?.to_string(), value))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Hosts named by an environment value: a bare `host`, `host:port`, or the
+/// host of a URL, possibly several separated by commas
+fn referenced_hosts(value: &str) -> Vec<&str> {
+    value
+        .split([',', ' '])
+        .filter_map(|part| {
+            let host = match part.split_once("://") {
+                Some((_, rest)) => {
+                    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+                    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+                    authority.split(':').next().unwrap_or_default()
+                }
+                None => {
+                    let (host, port) = part.split_once(':').unwrap_or((part, ""));
+                    if !port.chars().all(|c| c.is_ascii_digit()) {
+                        return None;
+                    }
+                    host
+                }
+            };
+            let valid = !host.is_empty() && host.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_');
+            valid.then_some(host)
+        })
+        .collect()
+}
+
+/// `container:NAME` in a mode key such as `network_mode`
+fn resolve_mode(service: &mut Mapping, key: &str, containers: &ContainerNames, references: &mut Vec<(String, String)>) {
+    let Some(value) = service.get_mut(key) else {
+        return;
+    };
+    let Some(target) = value
+        .as_str()
+        .and_then(|mode| mode.strip_prefix("container:"))
+        .and_then(|container| containers.get(container))
+    else {
+        return;
+    };
+
+    references.push((target.clone(), format!("{}: {}", key, value.as_str().unwrap_or_default())));
+    *value = Value::String(format!("service:{}", target));
+}
+
+/// `NAME[:MODE]` entries of `volumes_from`; unresolved names are marked as
+/// containers, since compose reads a bare name as a service
+fn resolve_volumes_from(service: &mut Mapping, containers: &ContainerNames, references: &mut Vec<(String, String)>) {
+    let Some(Value::Sequence(entries)) = service.get_mut("volumes_from") else {
+        return;
+    };
+
+    for entry in entries.iter_mut() {
+        let Some(spec) = entry.as_str().filter(|spec| !spec.starts_with("container:")) else {
+            continue;
+        };
+        let (container, mode) = match spec.split_once(':') {
+            Some((container, mode)) => (container, Some(mode)),
+            None => (spec, None),
+        };
+
+        let source = match containers.get(container) {
+            Some(target) => {
+                references.push((target.clone(), format!("--volumes-from {}", spec)));
+                target.clone()
+            }
+            None => format!("container:{}", container),
+        };
+        *entry = Value::String(match mode {
+            Some(mode) => format!("{}:{}", source, mode),
+            None => source,
+        });
+    }
+}
+
+/// `NAME[:ALIAS]` entries of `links`; resolved links are removed and their
+/// alias is recorded for the target service
+fn resolve_links(
+    service: &mut Mapping,
+    containers: &ContainerNames,
+    references: &mut Vec<(String, String)>,
+    aliases: &mut Vec<(String, String)>,
+    warnings: &mut Vec<String>,
+) {
+    let Some(Value::Sequence(entries)) = service.get_mut("links") else {
+        return;
+    };
+
+    entries.retain(|entry| {
+        let Some(spec) = entry.as_str() else {
+            return true;
+        };
+        let (container, alias) = spec.split_once(':').unwrap_or((spec, spec));
+        let Some(target) = containers.get(container) else {
+            warnings.push(format!("Link to '{}' kept: no command in the stack is named '{}'", spec, container));
+            return true;
+        };
+
+        // The service name already resolves on the shared network
+        if alias != target {
+            aliases.push((target.clone(), alias.to_string()));
+        }
+        references.push((target.clone(), format!("--link {}", spec)));
+        false
+    });
+
+    if entries.is_empty() {
+        service.remove("links");
+    }
+}
+
+/// Adds `alias` to every network of `target`, or to its default network
+fn add_alias(services: &mut Mapping, target: &str, alias: &str, warnings: &mut Vec<String>) {
+    let Some(Value::Mapping(service)) = services.get_mut(target) else {
+        return;
+    };
+    if service.contains_key("network_mode") {
+        warnings.push(format!(
+            "Cannot add network alias '{}' to service '{}' because it sets network_mode",
+            alias, target
+        ));
+        return;
+    }
+
+    let networks = service
+        .entry(Value::from("networks"))
+        .or_insert_with(|| Value::Mapping(Mapping::from_iter([(Value::from("default"), Value::Null)])));
+    let Value::Mapping(networks) = networks else {
+        return;
+    };
+    for (_, attachment) in networks.iter_mut() {
+        if attachment.is_null() {
+            *attachment = Value::Mapping(Mapping::new());
+        }
+        let Value::Mapping(attachment) = attachment else {
+            continue;
+        };
+        let aliases = attachment
+            .entry(Value::from("aliases"))
+            .or_insert_with(|| Value::Sequence(Vec::new()));
+        if let Value::Sequence(aliases) = aliases {
+            let alias = Value::from(alias);
+            if !aliases.contains(&alias) {
+                aliases.push(alias);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn services(yaml: &str) -> Mapping {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn containers(pairs: &[(&str, &str)]) -> ContainerNames {
+        pairs.iter().map(|(c, s)| (c.to_string(), s.to_string())).collect()
+    }
+
+    #[test]
+    fn test_resolves_modes_and_volumes_from() {
+        let mut services = services(
+            "db: {image: postgres}\n\
+             app: {image: app, network_mode: 'container:pg', pid: 'container:pg', volumes_from: ['pg:ro', other]}\n",
+        );
+        let mut warnings = Vec::new();
+        let dependencies = resolve_references(&mut services, &containers(&[("pg", "db")]), &mut warnings);
+
+        let app = &services["app"];
+        assert_eq!(app["network_mode"], Value::from("service:db"));
+        assert_eq!(app["pid"], Value::from("service:db"));
+        assert_eq!(app["volumes_from"], yaml("['db:ro', 'container:other']"));
+        let reasons: Vec<&str> = dependencies.iter().map(|d| d.reasons[0].as_str()).collect();
+        assert_eq!(reasons, vec!["network_mode: container:pg", "pid: container:pg", "--volumes-from pg:ro"]);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_links_become_aliases() {
+        let mut services = services(
+            "db: {image: postgres, networks: {backend: null}}\n\
+             cache: {image: redis}\n\
+             app: {image: app, links: ['pg:database', 'cache', 'legacy:old']}\n",
+        );
+        let mut warnings = Vec::new();
+        let dependencies =
+            resolve_references(&mut services, &containers(&[("pg", "db"), ("cache", "cache")]), &mut warnings);
+
+        assert_eq!(services["app"]["links"], yaml("['legacy:old']"));
+        let targets: Vec<&str> = dependencies.iter().map(|d| d.target.as_str()).collect();
+        assert_eq!(targets, vec!["db", "cache"]);
+        assert_eq!(services["db"]["networks"], yaml("{backend: {aliases: [database]}}"));
+        assert!(services["cache"].get("networks").is_none());
+        assert_eq!(warnings, vec!["Link to 'legacy:old' kept: no command in the stack is named 'legacy'"]);
+    }
+
+    #[test]
+    fn test_alias_on_default_network() {
+        let mut services = services("db: {image: postgres}\napp: {image: app, links: ['db:database']}\n");
+        resolve_references(&mut services, &containers(&[("db", "db")]), &mut Vec::new());
+        assert_eq!(services["db"]["networks"], yaml("{default: {aliases: [database]}}"));
+        assert!(services["app"].get("links").is_none());
+    }
+
+    #[test]
+    fn test_infers_hosts_from_environment() {
+        let services = services(
+            "db: {image: postgres, container_name: pg}\n\
+             cache: {image: redis, networks: {default: {aliases: [kv]}}}\n\
+             app: {image: app, environment: ['DB=postgres://u:p@PG:5432/app', 'CACHE=kv:6379', 'MODE=app', 'X=db.example']}\n\
+             worker: {image: worker, environment: {API: 'http://app/v1', BROKERS: 'cache:1,db:2'}}\n",
+        );
+        let edges: Vec<(String, String, String)> = infer_dependencies(&services)
+            .into_iter()
+            .map(|d| (d.service, d.target, d.reasons[0].clone()))
+            .collect();
+        let edge = |s: &str, t: &str, r: &str| (s.to_string(), t.to_string(), r.to_string());
+        assert_eq!(
+            edges,
+            vec![
+                edge("app", "db", "environment DB=postgres://u:p@PG:5432/app"),
+                edge("app", "cache", "environment CACHE=kv:6379"),
+                edge("worker", "app", "environment API=http://app/v1"),
+                edge("worker", "cache", "environment BROKERS=cache:1,db:2"),
+                edge("worker", "db", "environment BROKERS=cache:1,db:2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_depends_on_conditions_and_cycles() {
+        let mut services = services(
+            "db: {image: postgres, healthcheck: {test: [CMD, pg_isready]}}\n\
+             off: {image: x, healthcheck: {test: [CMD, x], disable: true}}\n\
+             app: {image: app}\n\
+             worker: {image: worker}\n",
+        );
+        let dependencies = vec![
+            Dependency::new("app", "db", "--link db".to_string()),
+            Dependency::new("app", "off", "environment OFF=off".to_string()),
+            Dependency::new("app", "db", "environment DB=db".to_string()),
+            Dependency::new("worker", "app", "environment APP=app".to_string()),
+            Dependency::new("db", "worker", "environment W=worker".to_string()),
+        ];
+        let mut warnings = Vec::new();
+        let edges = add_depends_on(&mut services, dependencies, &mut warnings);
+
+        assert_eq!(
+            services["app"]["depends_on"],
+            yaml("{db: {condition: service_healthy}, off: {condition: service_started}}")
+        );
+        assert_eq!(services["worker"]["depends_on"], yaml("[app]"));
+        assert!(services["db"].get("depends_on").is_none());
+        assert_eq!(edges[0].reasons, vec!["--link db", "environment DB=db"]);
+        assert_eq!(edges.len(), 3);
+        assert_eq!(
+            warnings,
+            vec!["Not adding depends_on from 'db' to 'worker': 'worker' already depends on 'db'"]
+        );
+    }
+
+    fn yaml(yaml: &str) -> Value {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+}
@@ -0,0 +1,231 @@
+//! Durations and byte sizes as docker flags take them and as compose writes
+//! them.
+
+use std::time::Duration;
+
+// Units of Go's time.ParseDuration, which both docker and compose use
+const DURATION_UNITS: &[(&str, u128)] = &[
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ns", 1),
+];
+
+/// Parses a Go duration such as `30s`, `1m30s`, `1.5h` or `250ms`
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = |reason: &str| format!("Invalid duration '{}': {}", value, reason);
+    if value == "0" {
+        return Ok(Duration::ZERO);
+    }
+    if value.is_empty() {
+        return Err(invalid("empty value"));
+    }
+    if value.starts_with('-') {
+        return Err(invalid("must not be negative"));
+    }
+
+    let mut nanos: u128 = 0;
+    let mut rest = value.strip_prefix('+').unwrap_or(value);
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(number_len);
+        let unit_len = tail.find(|c: char| c.is_ascii_digit() || c == '.').unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if (whole.is_empty() && fraction.is_empty()) || fraction.contains('.') {
+            return Err(invalid("expected a number"));
+        }
+        if unit.is_empty() {
+            return Err(invalid("missing unit, e.g. 30s"));
+        }
+        let scale = DURATION_UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .map(|(_, scale)| *scale)
+            .ok_or_else(|| invalid(&format!("unknown unit '{}', expected h, m, s, ms, us or ns", unit)))?;
+
+        let whole: u128 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| invalid("number too large"))?
+        };
+        // Fractions finer than a nanosecond are dropped, as Go does
+        let mut fraction_nanos = 0;
+        let mut divisor = 1;
+        for digit in fraction.chars().filter_map(|c| c.to_digit(10)) {
+            divisor *= 10;
+            fraction_nanos += digit as u128 * scale / divisor;
+        }
+        nanos = whole
+            .checked_mul(scale)
+            .and_then(|n| n.checked_add(fraction_nanos))
+            .and_then(|n| n.checked_add(nanos))
+            .ok_or_else(|| invalid("number too large"))?;
+        rest = tail;
+    }
+
+    let secs = u64::try_from(nanos / 1_000_000_000).map_err(|_| invalid("number too large"))?;
+    Ok(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
+
+/// Writes a duration with the largest units first, e.g. `1m30s` or `1s500ms`
+pub fn format_duration(duration: Duration) -> String {
+    if duration.is_zero() {
+        return "0s".to_string();
+    }
+    let mut nanos = duration.as_nanos();
+    let mut formatted = String::new();
+    for (unit, scale) in DURATION_UNITS.iter().filter(|(unit, _)| *unit != "µs") {
+        let count = nanos / scale;
+        if count > 0 {
+            formatted.push_str(&format!("{}{}", count, unit));
+            nanos %= scale;
+        }
+    }
+    formatted
+}
+
+/// Validates a duration and writes it in the form compose prefers
+pub fn normalize_duration(value: &str) -> Result<String, String> {
+    parse_duration(value).map(format_duration)
+}
+
+/// `--stop-timeout`, whole seconds, as a compose duration. Negative values
+/// are dropped with a warning before this is reached.
+pub fn seconds_to_duration(value: &str) -> Result<String, String> {
+    let seconds: u64 = value
+        .parse()
+        .map_err(|_| format!("Invalid number of seconds '{}': expected a whole number", value))?;
+    Ok(format_duration(Duration::from_secs(seconds)))
+}
+
+// Binary multiples, as docker's RAMInBytes uses for every unit
+const BYTE_UNITS: &[(char, u64)] = &[
+    ('k', 1 << 10),
+    ('m', 1 << 20),
+    ('g', 1 << 30),
+    ('t', 1 << 40),
+    ('p', 1 << 50),
+];
+
+/// Parses a size such as `512m`, `1g`, `1.5GiB` or `1024`, in bytes
+pub fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let invalid = |reason: &str| format!("Invalid size '{}': {}", value, reason);
+    let number_len = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(number_len);
+    if number.is_empty() {
+        return Err(invalid(if value.starts_with('-') { "must not be negative" } else { "expected a number" }));
+    }
+    let number: f64 = number.parse().map_err(|_| invalid("expected a number"))?;
+
+    // `m`, `mb` and `mib` all mean mebibytes
+    let unit = unit.trim_start().to_ascii_lowercase();
+    let multiplier = match unit.as_str() {
+        "" | "b" => Some(1),
+        _ => {
+            let (letter, suffix) = unit.split_at(unit.chars().next().map_or(0, char::len_utf8));
+            BYTE_UNITS
+                .iter()
+                .find(|(name, _)| name.to_string() == letter && matches!(suffix, "" | "b" | "ib"))
+                .map(|(_, multiplier)| *multiplier)
+        }
+    };
+    let multiplier = multiplier
+        .ok_or_else(|| invalid(&format!("unknown unit '{}', expected b, k, m, g, t or p", unit)))?;
+
+    let bytes = number * multiplier as f64;
+    if bytes > u64::MAX as f64 {
+        return Err(invalid("too large"));
+    }
+    Ok(bytes as u64)
+}
+
+/// Writes a size with the largest unit that divides it, e.g. `512m`
+pub fn format_byte_size(bytes: u64) -> String {
+    let unit = BYTE_UNITS
+        .iter()
+        .rev()
+        .find(|(_, multiplier)| bytes > 0 && bytes.is_multiple_of(*multiplier));
+    match unit {
+        Some((unit, multiplier)) => format!("{}{}", bytes / multiplier, unit),
+        None => format!("{}b", bytes),
+    }
+}
+
+/// Validates a size and writes it in the form compose prefers
+pub fn normalize_byte_size(value: &str) -> Result<String, String> {
+    parse_byte_size(value).map(format_byte_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_duration() {
+        let cases = [
+            ("30s", "30s"),
+            ("90s", "1m30s"),
+            ("1.5h", "1h30m"),
+            ("1500ms", "1s500ms"),
+            ("2m0s", "2m"),
+            ("0", "0s"),
+            ("10us", "10us"),
+            ("1h1m1s1ms", "1h1m1s1ms"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize_duration(input).unwrap(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_invalid_durations() {
+        let error = |value: &str| parse_duration(value).unwrap_err();
+        assert!(error("30").contains("missing unit"));
+        assert!(error("10x").contains("unknown unit 'x'"));
+        assert!(error("-5s").contains("negative"));
+        assert!(error("s").contains("expected a number"));
+        assert!(error("1.5.5s").contains("expected a number"));
+        assert!(error("").contains("empty"));
+    }
+
+    #[test]
+    fn test_seconds_to_duration() {
+        assert_eq!(seconds_to_duration("10").unwrap(), "10s");
+        assert_eq!(seconds_to_duration("120").unwrap(), "2m");
+        assert_eq!(seconds_to_duration("0").unwrap(), "0s");
+        assert!(seconds_to_duration("10s").unwrap_err().contains("whole number"));
+    }
+
+    #[test]
+    fn test_byte_sizes() {
+        let cases = [
+            ("512m", "512m"),
+            ("512M", "512m"),
+            ("1g", "1g"),
+            ("1024m", "1g"),
+            ("1.5g", "1536m"),
+            ("64MiB", "64m"),
+            ("2 GB", "2g"),
+            ("1t", "1t"),
+            ("1.5t", "1536g"),
+            ("1000", "1000b"),
+            ("2048b", "2k"),
+            ("0", "0b"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize_byte_size(input).unwrap(), expected, "input: {}", input);
+        }
+
+        let error = |value: &str| parse_byte_size(value).unwrap_err();
+        assert!(error("512x").contains("unknown unit 'x'"));
+        assert!(error("1gbb").contains("unknown unit"));
+        assert!(error("-1g").contains("negative"));
+        assert!(error("lots").contains("expected a number"));
+        assert!(error("1..5g").contains("expected a number"));
+    }
+}
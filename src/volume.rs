@@ -0,0 +1,496 @@
+//! Volume specs of `-v`, relative bind mount paths and top-level volume
+//! definitions from `docker volume create`.
+
+use crate::network::key_values;
+use crate::parser::{parse_subcommand_args, SubcommandFlag};
+use serde_yaml::{Mapping, Value};
+use std::path::PathBuf;
+
+const FLAGS: &[SubcommandFlag] = &[
+    SubcommandFlag { long: "driver", short: Some('d'), takes_value: true },
+    SubcommandFlag { long: "opt", short: Some('o'), takes_value: true },
+    SubcommandFlag { long: "label", short: None, takes_value: true },
+    SubcommandFlag { long: "name", short: None, takes_value: true },
+    // Cluster volume settings without a compose equivalent
+    SubcommandFlag { long: "availability", short: None, takes_value: true },
+    SubcommandFlag { long: "group", short: None, takes_value: true },
+    SubcommandFlag { long: "limit-bytes", short: None, takes_value: true },
+    SubcommandFlag { long: "required-bytes", short: None, takes_value: true },
+    SubcommandFlag { long: "scope", short: None, takes_value: true },
+    SubcommandFlag { long: "secret", short: None, takes_value: true },
+    SubcommandFlag { long: "sharing", short: None, takes_value: true },
+    SubcommandFlag { long: "topology-preferred", short: None, takes_value: true },
+    SubcommandFlag { long: "topology-required", short: None, takes_value: true },
+    SubcommandFlag { long: "type", short: None, takes_value: true },
+];
+
+const CLUSTER_FLAGS: &[&str] = &[
+    "availability", "group", "limit-bytes", "required-bytes", "scope", "secret", "sharing",
+    "topology-preferred", "topology-required", "type",
+];
+
+/// A volume created by a `docker volume create` command
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CreatedVolume {
+    pub name: String,
+    /// Entry for the top-level `volumes` section
+    pub definition: Mapping,
+    /// Problems that did not prevent conversion
+    pub warnings: Vec<String>,
+}
+
+/// Parses the arguments after `volume create`
+pub fn parse_volume_create(tokens: &[String]) -> Result<CreatedVolume, String> {
+    let (args, positional) = parse_subcommand_args(tokens, FLAGS, "volume create")?;
+    let values = |flag: &str| args.get(flag).map(Vec::as_slice).unwrap_or_default();
+
+    let name = match (positional.as_slice(), values("name").last()) {
+        ([name], None) | ([], Some(name)) => name.clone(),
+        ([], None) => {
+            return Err("volume create requires a volume name, since compose cannot refer to an anonymous one".to_string())
+        }
+        ([name], Some(flag)) if name == flag => name.clone(),
+        ([_], Some(_)) => return Err("volume create got both a name and a different --name".to_string()),
+        ([_, extra, ..], _) => return Err(format!("Unexpected argument '{}' for volume create", extra)),
+    };
+
+    let mut definition = Mapping::new();
+    if let Some(driver) = values("driver").last() {
+        definition.insert(Value::from("driver"), Value::from(driver.as_str()));
+    }
+    if let Some(opts) = key_values(values("opt"), "--opt")? {
+        definition.insert(Value::from("driver_opts"), opts);
+    }
+    if let Some(labels) = key_values(values("label"), "--label")? {
+        definition.insert(Value::from("labels"), labels);
+    }
+
+    let warnings = CLUSTER_FLAGS
+        .iter()
+        .filter(|flag| args.contains_key(**flag))
+        .map(|flag| format!("Ignoring --{} of volume '{}': it has no compose equivalent", flag, name))
+        .collect();
+
+    Ok(CreatedVolume { name, definition, warnings })
+}
+
+/// Where the data of a `-v` mount comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VolumeSource {
+    /// `-v /data`: a fresh volume only this container uses
+    Anonymous,
+    /// A volume that can be declared at the top level
+    Named(String),
+    /// A host path, possibly relative, `~`-based, a Windows drive path or
+    /// starting with a variable
+    Bind(String),
+}
+
+/// Options after the second colon of a `-v` spec
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeModes {
+    /// `ro` or `rw`, as given
+    pub access: Option<String>,
+    /// `z` (shared) or `Z` (private) SELinux relabeling
+    pub selinux: Option<char>,
+    pub nocopy: bool,
+    /// `shared`, `rslave`, ...
+    pub propagation: Option<String>,
+    /// `consistent`, `cached` or `delegated` (Docker Desktop for Mac)
+    pub consistency: Option<String>,
+}
+
+const PROPAGATIONS: &[&str] = &["shared", "rshared", "slave", "rslave", "private", "rprivate"];
+const CONSISTENCIES: &[&str] = &["consistent", "cached", "delegated", "default"];
+
+/// A parsed `-v`/`--volume` value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeSpec {
+    pub source: VolumeSource,
+    pub target: String,
+    pub modes: VolumeModes,
+    /// Modes in the order they were given
+    mode_list: Vec<String>,
+}
+
+impl VolumeSpec {
+    /// Short compose syntax, `SOURCE:TARGET[:MODES]`
+    pub fn to_short_syntax(&self) -> String {
+        let mut parts = Vec::new();
+        match &self.source {
+            VolumeSource::Anonymous => {}
+            VolumeSource::Named(name) => parts.push(name.as_str()),
+            VolumeSource::Bind(path) => parts.push(path.as_str()),
+        }
+        parts.push(&self.target);
+        let modes = self.mode_list.join(",");
+        if !modes.is_empty() {
+            parts.push(&modes);
+        }
+        parts.join(":")
+    }
+}
+
+/// Parses `[SOURCE:]TARGET[:MODES]`. Colons of Windows drive letters and
+/// inside `${...}` do not separate fields.
+pub fn parse_volume_spec(spec: &str) -> Result<VolumeSpec, String> {
+    let fields = split_fields(spec);
+    let (source, target, modes) = match fields.as_slice() {
+        [target] => (None, target.as_str(), None),
+        [source, target] => (Some(source.as_str()), target.as_str(), None),
+        [source, target, modes] => (Some(source.as_str()), target.as_str(), Some(modes.as_str())),
+        _ => return Err(format!("Invalid volume '{}': too many colon-separated fields", spec)),
+    };
+
+    if !is_absolute(target) && !target.starts_with('$') {
+        return Err(format!("Invalid volume '{}': container path '{}' must be absolute", spec, target));
+    }
+
+    let source = match source {
+        None => VolumeSource::Anonymous,
+        Some("") => return Err(format!("Invalid volume '{}': empty source", spec)),
+        Some(path) if is_host_path(path) => VolumeSource::Bind(path.to_string()),
+        Some(name) if is_volume_name(name) => VolumeSource::Named(name.to_string()),
+        Some(name) => {
+            return Err(format!(
+                "Invalid volume '{}': '{}' is neither a volume name nor a host path (prefix relative paths with ./)",
+                spec, name
+            ))
+        }
+    };
+
+    let mut parsed = VolumeModes::default();
+    let mut mode_list = Vec::new();
+    for mode in modes.into_iter().flat_map(|modes| modes.split(',')) {
+        let slot = match mode {
+            "ro" | "rw" => parsed.access.replace(mode.to_string()).is_some(),
+            "z" | "Z" => parsed.selinux.replace(mode.chars().next().unwrap_or('z')).is_some(),
+            "nocopy" => std::mem::replace(&mut parsed.nocopy, true),
+            m if PROPAGATIONS.contains(&m) => parsed.propagation.replace(m.to_string()).is_some(),
+            m if CONSISTENCIES.contains(&m) => parsed.consistency.replace(m.to_string()).is_some(),
+            _ => return Err(format!("Invalid volume mode '{}' in '{}'", mode, spec)),
+        };
+        if slot {
+            return Err(format!("Conflicting volume mode '{}' in '{}'", mode, spec));
+        }
+        mode_list.push(mode.to_string());
+    }
+    if parsed.nocopy && !matches!(source, VolumeSource::Named(_) | VolumeSource::Anonymous) {
+        return Err(format!("Invalid volume '{}': nocopy only applies to volumes, not bind mounts", spec));
+    }
+
+    Ok(VolumeSpec {
+        source,
+        target: target.to_string(),
+        modes: parsed,
+        mode_list,
+    })
+}
+
+/// Splits at colons that are not part of a drive letter or a `${...}`
+fn split_fields(spec: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let chars: Vec<char> = spec.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        match c {
+            '{' if i > 0 && chars[i - 1] == '$' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            ':' if depth == 0 => {
+                let drive = current.len() == 1
+                    && current.chars().all(|c| c.is_ascii_alphabetic())
+                    && matches!(chars.get(i + 1), Some('\\' | '/'));
+                if !drive {
+                    fields.push(std::mem::take(&mut current));
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    fields.push(current);
+    fields
+}
+
+fn is_absolute(path: &str) -> bool {
+    path.starts_with('/') || is_windows_path(path)
+}
+
+/// `C:\...`, `C:/...` or a `\\server\share` UNC path
+fn is_windows_path(path: &str) -> bool {
+    let mut chars = path.chars();
+    let drive = matches!(
+        (chars.next(), chars.next(), chars.next()),
+        (Some(letter), Some(':'), Some('\\' | '/')) if letter.is_ascii_alphabetic()
+    );
+    drive || path.starts_with("\\\\")
+}
+
+/// Anything compose reads as a bind mount rather than a volume name
+fn is_host_path(path: &str) -> bool {
+    is_absolute(path) || path.starts_with('.') || path.starts_with('~') || path.starts_with('$')
+}
+
+/// `[a-zA-Z0-9][a-zA-Z0-9_.-]*`, as docker accepts for local volumes
+fn is_volume_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c))
+}
+
+/// Rewrites absolute bind mount sources below a base directory so the
+/// compose file does not depend on where the project is checked out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindRewrite {
+    /// Directory the compose file is written to, as an absolute path
+    pub base: PathBuf,
+    /// Write `${VAR}/x` instead of `./x`
+    pub variable: Option<String>,
+}
+
+impl BindRewrite {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self {
+            base: base.into(),
+            variable: None,
+        }
+    }
+
+    pub fn with_variable(mut self, variable: &str) -> Self {
+        self.variable = Some(variable.to_string());
+        self
+    }
+
+    /// The relative form of `path` if it is the base directory or below it
+    pub fn rewrite(&self, path: &str) -> Option<String> {
+        if !is_absolute(path) {
+            return None;
+        }
+        let base = path_components(&self.base.to_string_lossy());
+        let path = path_components(path);
+        let windows = base.first().is_some_and(|root| root != "/");
+        let same = |a: &String, b: &String| if windows { a.eq_ignore_ascii_case(b) } else { a == b };
+        if path.len() < base.len() || !base.iter().zip(&path).all(|(a, b)| same(a, b)) {
+            return None;
+        }
+
+        let prefix = match &self.variable {
+            Some(variable) => format!("${{{}}}", variable),
+            None => ".".to_string(),
+        };
+        Some(std::iter::once(prefix).chain(path[base.len()..].iter().cloned()).collect::<Vec<_>>().join("/"))
+    }
+}
+
+/// A bind mount source replaced by [`rewrite_binds`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathRewrite {
+    pub service: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// Applies `rewrite` to the bind mounts of every service in `services`
+pub fn rewrite_binds(services: &mut Mapping, rewrite: &BindRewrite) -> Vec<PathRewrite> {
+    let mut rewrites = Vec::new();
+    for (name, service) in services.iter_mut() {
+        let service_name = name.as_str().unwrap_or_default();
+        let Some(Value::Sequence(volumes)) = service.get_mut("volumes") else {
+            continue;
+        };
+        for volume in volumes.iter_mut() {
+            let rewritten = match volume {
+                Value::String(entry) => rewrite_short(entry, rewrite),
+                Value::Mapping(entry) if entry.get("type").and_then(Value::as_str) == Some("bind") => {
+                    rewrite_long(entry, rewrite)
+                }
+                _ => None,
+            };
+            if let Some((from, to)) = rewritten {
+                rewrites.push(PathRewrite {
+                    service: service_name.to_string(),
+                    from,
+                    to,
+                });
+            }
+        }
+    }
+    rewrites
+}
+
+/// Rewrites the source of a short syntax entry, returning the old and new source
+fn rewrite_short(entry: &mut String, rewrite: &BindRewrite) -> Option<(String, String)> {
+    let mut spec = parse_volume_spec(entry).ok()?;
+    let VolumeSource::Bind(from) = &spec.source else {
+        return None;
+    };
+    let from = from.clone();
+    let to = rewrite.rewrite(&from)?;
+    spec.source = VolumeSource::Bind(to.clone());
+    *entry = spec.to_short_syntax();
+    Some((from, to))
+}
+
+fn rewrite_long(entry: &mut Mapping, rewrite: &BindRewrite) -> Option<(String, String)> {
+    let from = entry.get("source")?.as_str()?.to_string();
+    let to = rewrite.rewrite(&from)?;
+    entry.insert(Value::from("source"), Value::from(to.clone()));
+    Some((from, to))
+}
+
+/// Root (`/`, a lowercase drive like `c:` or `//` for UNC) followed by the
+/// normalized segments of an absolute path
+fn path_components(path: &str) -> Vec<String> {
+    let root = if path.starts_with("\\\\") {
+        "//".to_string()
+    } else if is_windows_path(path) {
+        path[..2].to_ascii_lowercase()
+    } else {
+        "/".to_string()
+    };
+    let rest = if root.ends_with(':') { &path[2..] } else { path };
+
+    let mut components = vec![root];
+    for segment in rest.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                if components.len() > 1 {
+                    components.pop();
+                }
+            }
+            segment => components.push(segment.to_string()),
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<CreatedVolume, String> {
+        parse_volume_create(&crate::shell::tokenize(args).unwrap())
+    }
+
+    #[test]
+    fn test_nfs_volume() {
+        let volume = parse(
+            "--driver local --opt type=nfs --opt o=addr=10.0.0.5,rw -o device=:/exports/data --label backup=daily data",
+        )
+        .unwrap();
+        let expected: Mapping = serde_yaml::from_str(
+            "driver: local\n\
+             driver_opts: {type: nfs, o: 'addr=10.0.0.5,rw', device: ':/exports/data'}\n\
+             labels: {backup: daily}\n",
+        )
+        .unwrap();
+        assert_eq!(volume.name, "data");
+        assert_eq!(volume.definition, expected);
+    }
+
+    #[test]
+    fn test_names_and_warnings() {
+        assert_eq!(parse("--name cache").unwrap().name, "cache");
+        assert!(parse("").unwrap_err().contains("requires a volume name"));
+        assert!(parse("--name a b").is_err());
+        assert!(parse("a b").is_err());
+
+        let volume = parse("--scope multi --sharing all shared").unwrap();
+        assert_eq!(volume.warnings.len(), 2);
+        assert!(volume.definition.is_empty());
+    }
+
+    #[test]
+    fn test_volume_sources() {
+        let source = |spec: &str| parse_volume_spec(spec).unwrap().source;
+        let bind = |path: &str| VolumeSource::Bind(path.to_string());
+        let named = |name: &str| VolumeSource::Named(name.to_string());
+
+        assert_eq!(source("/data"), VolumeSource::Anonymous);
+        assert_eq!(source("data:/data"), named("data"));
+        assert_eq!(source("my_vol.1:/data:ro"), named("my_vol.1"));
+        assert_eq!(source("/host:/data"), bind("/host"));
+        assert_eq!(source("./conf:/etc/app"), bind("./conf"));
+        assert_eq!(source("~/x:/x"), bind("~/x"));
+        assert_eq!(source("$PWD/x:/x"), bind("$PWD/x"));
+        assert_eq!(source("${HOME}/x:/x"), bind("${HOME}/x"));
+        assert_eq!(source("${DATA:-/srv/data}:/data:ro"), bind("${DATA:-/srv/data}"));
+        assert_eq!(source(r"C:\data:/data"), bind(r"C:\data"));
+        assert_eq!(source("c:/data:/data:ro"), bind("c:/data"));
+        assert_eq!(source(r"\\server\share:C:\app"), bind(r"\\server\share"));
+    }
+
+    #[test]
+    fn test_volume_modes() {
+        let spec = parse_volume_spec("/h:/c:ro,rshared,Z,cached").unwrap();
+        assert_eq!(spec.target, "/c");
+        assert_eq!(
+            spec.modes,
+            VolumeModes {
+                access: Some("ro".to_string()),
+                selinux: Some('Z'),
+                nocopy: false,
+                propagation: Some("rshared".to_string()),
+                consistency: Some("cached".to_string()),
+            }
+        );
+        assert_eq!(spec.to_short_syntax(), "/h:/c:ro,rshared,Z,cached");
+        assert!(parse_volume_spec("data:/data:nocopy").unwrap().modes.nocopy);
+        assert_eq!(parse_volume_spec("/data").unwrap().to_short_syntax(), "/data");
+    }
+
+    #[test]
+    fn test_invalid_volume_specs() {
+        let error = |spec: &str| parse_volume_spec(spec).unwrap_err();
+        assert!(error("data:/data:rx").contains("Invalid volume mode 'rx'"));
+        assert!(error("data:/data:ro,rw").contains("Conflicting volume mode 'rw'"));
+        assert!(error("data:relative").contains("must be absolute"));
+        assert!(error("data:/b:ro:extra").contains("too many"));
+        assert!(error("foo/bar:/x").contains("neither a volume name nor a host path"));
+        assert!(error("/h:/c:nocopy").contains("nocopy"));
+    }
+
+    #[test]
+    fn test_bind_rewrite() {
+        let rewrite = BindRewrite::new("/home/alice/proj");
+        assert_eq!(rewrite.rewrite("/home/alice/proj/config").as_deref(), Some("./config"));
+        assert_eq!(rewrite.rewrite("/home/alice/proj/").as_deref(), Some("."));
+        assert_eq!(rewrite.rewrite("/home/alice/proj/a/../b").as_deref(), Some("./b"));
+        assert_eq!(rewrite.rewrite("/home/alice/project"), None);
+        assert_eq!(rewrite.rewrite("/home/alice"), None);
+        assert_eq!(rewrite.rewrite("./config"), None);
+
+        let rewrite = BindRewrite::new(r"C:\Users\alice\proj").with_variable("DATA_DIR");
+        assert_eq!(rewrite.rewrite(r"c:\users\alice\proj\db\data").as_deref(), Some("${DATA_DIR}/db/data"));
+        assert_eq!(rewrite.rewrite("/Users/alice/proj/db"), None);
+    }
+
+    #[test]
+    fn test_rewrite_binds() {
+        let mut services: Mapping = serde_yaml::from_str(
+            "app:\n  volumes: ['/srv/app/conf:/etc/app:ro', 'data:/data', '/etc/ssl:/ssl', {type: bind, source: /srv/app/logs, target: /logs}]",
+        )
+        .unwrap();
+        let rewrites = rewrite_binds(&mut services, &BindRewrite::new("/srv/app"));
+        assert_eq!(
+            services["app"]["volumes"],
+            serde_yaml::from_str::<Value>(
+                "['./conf:/etc/app:ro', 'data:/data', '/etc/ssl:/ssl', {type: bind, source: ./logs, target: /logs}]"
+            )
+            .unwrap()
+        );
+        assert_eq!(rewrites.len(), 2);
+        assert_eq!(
+            rewrites[0],
+            PathRewrite {
+                service: "app".to_string(),
+                from: "/srv/app/conf".to_string(),
+                to: "./conf".to_string(),
+            }
+        );
+    }
+}
@@ -81,7 +81,7 @@ fn test_node_app_with_command() {
     assert!(yaml.contains("NODE_ENV=production"));
     assert!(yaml.contains("/app:/usr/src/app"));
     assert!(yaml.contains("working_dir: /usr/src/app"));
-    assert!(yaml.contains("command: npm start"));
+    assert!(yaml.contains("command:\n    - npm\n    - start"));
 }
 
 #[test]
@@ -137,7 +137,7 @@ fn test_interactive_shell() {
     let yaml = result.unwrap();
     assert!(yaml.contains("stdin_open: true"));
     assert!(yaml.contains("tty: true"));
-    assert!(yaml.contains("command: bash"));
+    assert!(yaml.contains("command:\n    - bash"));
 }
 
 #[test]
@@ -262,3 +262,453 @@ fn test_security_options() {
     assert!(yaml.contains("cap_add:"));
     assert!(yaml.contains("NET_BIND_SERVICE"));
 }
+
+#[test]
+fn test_yaml_and_json_outputs_agree() {
+    use composerize_np::options::{ComposeVersion, ComposerizeOptions, EnvStyle, OutputFormat};
+    use composerize_np::Composerizer;
+
+    let inputs = [
+        "docker run -d --name postgres-db -p 5432:5432 -e POSTGRES_PASSWORD=secret -v pgdata:/var/lib/postgresql/data --restart unless-stopped postgres:15-alpine",
+        "docker run -d --name redis-cache --network backend --health-cmd 'redis-cli ping' redis:alpine",
+        "docker run -it --rm -e A -e B=2 --entrypoint /bin/sh ubuntu -c 'echo \"a\nb\"'",
+        "docker run --ulimit nofile=1024:2048 --log-opt max-size=10m --tmpfs /tmp nginx",
+    ];
+
+    for input in inputs {
+        for indent in [0, 2, 4] {
+            let options = ComposerizeOptions::new()
+                .with_version(ComposeVersion::V3)
+                .with_env_style(EnvStyle::Map)
+                .with_indent(indent);
+            let yaml = Composerizer::new(options.clone().with_output_format(OutputFormat::Yaml))
+                .convert(input)
+                .unwrap()
+                .output;
+            let json = Composerizer::new(options.with_output_format(OutputFormat::Json))
+                .convert(input)
+                .unwrap()
+                .output;
+
+            let from_yaml: serde_json::Value = serde_yaml::from_str(&yaml).unwrap();
+            let from_json: serde_json::Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(from_yaml, from_json, "input: {} indent: {}", input, indent);
+        }
+    }
+}
+
+#[test]
+fn test_indent_applies_to_yaml() {
+    let yaml = composerize("docker run -p 80:80 nginx", "", "latest", 4).unwrap();
+    assert!(yaml.contains("services:\n    nginx:\n        ports:\n        - 80:80"));
+}
+
+#[test]
+fn test_stack_resolves_container_references() {
+    let input = "docker run -d --name pg postgres:16\n\
+        docker run -d --name web --link pg:database --volumes-from pg:ro nginx\n\
+        docker run --network container:web --pid container:web busybox";
+    let yaml = composerize(input, "", "latest", 2).unwrap();
+    let compose: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+    let services = &compose["services"];
+
+    assert_eq!(services["pg"]["networks"]["default"]["aliases"][0], "database");
+    assert!(services["web"].get("links").is_none());
+    assert_eq!(services["web"]["volumes_from"][0], "pg:ro");
+    assert_eq!(services["web"]["depends_on"][0], "pg");
+    assert_eq!(services["busybox"]["network_mode"], "service:web");
+    assert_eq!(services["busybox"]["pid"], "service:web");
+    assert_eq!(services["busybox"]["depends_on"][0], "web");
+    assert!(compose.get("networks").is_none());
+}
+
+#[test]
+fn test_stack_keeps_duplicate_services() {
+    let yaml = composerize("docker run redis; docker run redis", "", "latest", 2).unwrap();
+    assert!(yaml.contains("  redis:\n    image: redis\n  redis-2:\n    image: redis\n"));
+}
+
+#[test]
+fn test_stack_depends_on_inference() {
+    use composerize_np::options::ComposerizeOptions;
+    use composerize_np::Composerizer;
+
+    let input = "docker run --name db --health-cmd pg_isready postgres; docker run -e DB_HOST=db app";
+    let conversion = Composerizer::default().convert(input).unwrap();
+    assert!(conversion.output.contains("    depends_on:\n      db:\n        condition: service_healthy\n"));
+    assert_eq!(conversion.dependencies[0].reasons, vec!["environment DB_HOST=db"]);
+
+    let options = ComposerizeOptions::new().with_infer_depends_on(false);
+    let conversion = Composerizer::new(options.clone()).convert(input).unwrap();
+    assert!(!conversion.output.contains("depends_on"));
+    assert!(conversion.dependencies.is_empty());
+
+    // Explicit references keep their ordering without inference
+    let input = "docker run --name db postgres; docker run --link db -e DB_HOST=db app";
+    let conversion = Composerizer::new(options).convert(input).unwrap();
+    assert!(conversion.output.contains("    depends_on:\n    - db\n"));
+    assert_eq!(conversion.dependencies[0].reasons, vec!["--link db"]);
+}
+
+#[test]
+fn test_network_policies() {
+    use composerize_np::options::{ComposerizeOptions, ResourcePolicy};
+    use composerize_np::Composerizer;
+
+    let input = "docker network create --internal --subnet 10.9.0.0/24 backend && \
+                 docker run --network backend --network frontend nginx";
+    let convert = |policy| {
+        let output = Composerizer::new(ComposerizeOptions::new().with_network_policy(policy))
+            .convert(input)
+            .unwrap()
+            .output;
+        serde_yaml::from_str::<serde_yaml::Value>(&output).unwrap()["networks"].clone()
+    };
+    let yaml = |s: &str| serde_yaml::from_str::<serde_yaml::Value>(s).unwrap();
+
+    assert_eq!(
+        convert(ResourcePolicy::Auto),
+        yaml("{backend: {ipam: {config: [{subnet: 10.9.0.0/24}]}, internal: true}, frontend: {external: true}}")
+    );
+    assert_eq!(
+        convert(ResourcePolicy::External),
+        yaml("{backend: {external: true}, frontend: {external: true}}")
+    );
+    assert_eq!(
+        convert(ResourcePolicy::Managed),
+        yaml("{backend: {ipam: {config: [{subnet: 10.9.0.0/24}]}, internal: true}, frontend: null}")
+    );
+
+    let error = composerize("docker network create backend", "", "latest", 2).unwrap_err();
+    assert_eq!(error, "No docker run command to convert");
+}
+
+#[test]
+fn test_volume_create_and_policies() {
+    use composerize_np::options::{ComposerizeOptions, ResourcePolicy};
+    use composerize_np::Composerizer;
+
+    let input = "docker volume create --driver local --opt type=nfs --opt o=addr=10.0.0.5 --opt device=:/srv data\n\
+                 docker volume create spare\n\
+                 docker run -v data:/data -v cache:/cache -v /host:/host nginx";
+    let convert = |policy| {
+        let output = Composerizer::new(ComposerizeOptions::new().with_volume_policy(policy))
+            .convert(input)
+            .unwrap()
+            .output;
+        serde_yaml::from_str::<serde_yaml::Value>(&output).unwrap()
+    };
+    let yaml = |s: &str| serde_yaml::from_str::<serde_yaml::Value>(s).unwrap();
+
+    let compose = convert(ResourcePolicy::Auto);
+    assert_eq!(
+        compose["volumes"],
+        yaml("{data: {driver: local, driver_opts: {type: nfs, o: addr=10.0.0.5, device: ':/srv'}}, cache: null, spare: {}}")
+    );
+    assert_eq!(compose["services"]["nginx"]["volumes"][0], "data:/data");
+    assert_eq!(
+        convert(ResourcePolicy::External)["volumes"],
+        yaml("{data: {external: true}, cache: {external: true}, spare: {external: true}}")
+    );
+}
+
+#[test]
+fn test_volume_specs() {
+    let input = r"docker run -v 'C:\data:/data' -v $PWD/conf:/conf:ro,z -v ${HOME}/x:/x -v /scratch --volume cache:/cache:nocopy nginx";
+    let compose: serde_yaml::Value = serde_yaml::from_str(&composerize(input, "", "latest", 2).unwrap()).unwrap();
+    let volumes = compose["services"]["nginx"]["volumes"].as_sequence().unwrap();
+    assert_eq!(volumes.len(), 5);
+    assert_eq!(volumes[1], "${PWD:-.}/conf:/conf:ro,z");
+    assert_eq!(
+        compose["volumes"],
+        serde_yaml::from_str::<serde_yaml::Value>("{cache: null}").unwrap()
+    );
+
+    let error = composerize("docker run -v data:/data:rox nginx", "", "latest", 2).unwrap_err();
+    assert!(error.contains("Invalid volume mode 'rox'"));
+}
+
+#[test]
+fn test_relative_bind_mounts() {
+    use composerize_np::options::ComposerizeOptions;
+    use composerize_np::volume::BindRewrite;
+    use composerize_np::Composerizer;
+
+    let input = "docker run -v /home/alice/proj/config:/etc/app:ro -v /home/alice/other:/other nginx";
+    let convert = |rewrite| Composerizer::new(ComposerizeOptions::new().with_bind_rewrite(rewrite)).convert(input).unwrap();
+
+    let conversion = convert(BindRewrite::new("/home/alice/proj"));
+    assert!(conversion.output.contains("- ./config:/etc/app:ro"));
+    assert!(conversion.output.contains("- /home/alice/other:/other"));
+    assert_eq!(conversion.path_rewrites.len(), 1);
+    assert_eq!(conversion.path_rewrites[0].from, "/home/alice/proj/config");
+    assert!(conversion.warnings.is_empty());
+
+    let conversion = convert(BindRewrite::new("/home/alice").with_variable("DATA_DIR"));
+    assert!(conversion.output.contains("- ${DATA_DIR}/proj/config:/etc/app:ro"));
+    assert!(conversion.output.contains("- ${DATA_DIR}/other:/other"));
+    assert_eq!(conversion.path_rewrites.len(), 2);
+}
+
+#[test]
+fn test_typed_sizes_and_durations() {
+    let input = "docker run --memory 1536M --memory-swap -1 --shm-size 64MiB --stop-timeout 10 --memory-swappiness 10 nginx";
+    let compose: serde_yaml::Value = serde_yaml::from_str(&composerize(input, "", "latest", 2).unwrap()).unwrap();
+    let service = &compose["services"]["nginx"];
+    assert_eq!(service["deploy"]["resources"]["limits"]["memory"], "1536m");
+    assert_eq!(service["memswap_limit"], -1);
+    assert_eq!(service["shm_size"], "64m");
+    assert_eq!(service["stop_grace_period"], "10s");
+    assert_eq!(service["mem_swappiness"], 10);
+
+    let error = composerize("docker run --shm-size 1x nginx", "", "latest", 2).unwrap_err();
+    assert!(error.contains("Invalid size '1x': unknown unit 'x'"));
+    let error = composerize("docker run --stop-timeout 10s nginx", "", "latest", 2).unwrap_err();
+    assert!(error.contains("Invalid number of seconds '10s'"));
+
+    let conversion = composerize_np::Composerizer::default()
+        .convert("docker run --stop-timeout -1 nginx")
+        .unwrap();
+    assert!(!conversion.output.contains("stop_grace_period"));
+    assert_eq!(
+        conversion.warnings,
+        vec!["Ignoring --stop-timeout -1: waiting forever has no compose equivalent"]
+    );
+}
+
+#[test]
+fn test_every_docker_run_flag() {
+    use composerize_np::mappings::{get_mappings, ArgType, DOCKER_RUN_FLAGS};
+
+    // Flag, its arguments, and the service it produces without `image`
+    let cases: &[(&str, &str, &str)] = &[
+        ("add-host", "--add-host db:10.0.0.2", "{extra_hosts: ['db:10.0.0.2']}"),
+        ("annotation", "--annotation com.example.x=1", "{annotations: [com.example.x=1]}"),
+        ("attach", "--attach stdout", "{}"),
+        ("a", "-a stderr", "{}"),
+        ("blkio-weight", "--blkio-weight 300", "{blkio_config: {weight: 300}}"),
+        (
+            "blkio-weight-device",
+            "--blkio-weight-device /dev/sda:200",
+            "{blkio_config: {weight_device: [{path: /dev/sda, weight: 200}]}}",
+        ),
+        ("cap-add", "--cap-add NET_ADMIN", "{cap_add: [NET_ADMIN]}"),
+        ("cap-drop", "--cap-drop ALL", "{cap_drop: [ALL]}"),
+        ("cgroup-parent", "--cgroup-parent /ci", "{cgroup_parent: /ci}"),
+        ("cgroupns", "--cgroupns host", "{cgroup: host}"),
+        ("cidfile", "--cidfile /tmp/id", "{}"),
+        ("cpu-count", "--cpu-count 2", "{cpu_count: 2}"),
+        ("cpu-percent", "--cpu-percent 50", "{cpu_percent: 50}"),
+        ("cpu-period", "--cpu-period 100000", "{cpu_period: 100000}"),
+        ("cpu-quota", "--cpu-quota 50000", "{cpu_quota: 50000}"),
+        ("cpu-rt-period", "--cpu-rt-period 1000000", "{cpu_rt_period: 1000000}"),
+        ("cpu-rt-runtime", "--cpu-rt-runtime 950000", "{cpu_rt_runtime: 950000}"),
+        ("cpu-shares", "--cpu-shares 512", "{cpu_shares: 512}"),
+        ("c", "-c 256", "{cpu_shares: 256}"),
+        ("cpus", "--cpus 1.5", "{deploy: {resources: {limits: {cpus: 1.5}}}}"),
+        ("cpuset-cpus", "--cpuset-cpus 0-2", "{cpuset: 0-2}"),
+        ("cpuset-mems", "--cpuset-mems 0", "{}"),
+        ("detach", "--detach", "{}"),
+        ("d", "-d", "{}"),
+        ("detach-keys", "--detach-keys ctrl-x", "{}"),
+        ("device", "--device /dev/fuse", "{devices: [/dev/fuse]}"),
+        ("device-cgroup-rule", "--device-cgroup-rule 'c 1:3 mr'", "{device_cgroup_rules: ['c 1:3 mr']}"),
+        (
+            "device-read-bps",
+            "--device-read-bps /dev/sda:1mb",
+            "{blkio_config: {device_read_bps: [{path: /dev/sda, rate: 1m}]}}",
+        ),
+        (
+            "device-read-iops",
+            "--device-read-iops /dev/sda:1000",
+            "{blkio_config: {device_read_iops: [{path: /dev/sda, rate: 1000}]}}",
+        ),
+        (
+            "device-write-bps",
+            "--device-write-bps /dev/sda:10M",
+            "{blkio_config: {device_write_bps: [{path: /dev/sda, rate: 10m}]}}",
+        ),
+        (
+            "device-write-iops",
+            "--device-write-iops /dev/sda:500",
+            "{blkio_config: {device_write_iops: [{path: /dev/sda, rate: 500}]}}",
+        ),
+        ("disable-content-trust", "--disable-content-trust", "{}"),
+        ("dns", "--dns 1.1.1.1", "{dns: [1.1.1.1]}"),
+        ("dns-opt", "--dns-opt ndots:2", "{dns_opt: ['ndots:2']}"),
+        ("dns-option", "--dns-option ndots:3", "{dns_opt: ['ndots:3']}"),
+        ("dns-search", "--dns-search example.com", "{dns_search: [example.com]}"),
+        ("domainname", "--domainname example.com", "{domainname: example.com}"),
+        ("entrypoint", "--entrypoint /bin/sh", "{entrypoint: [/bin/sh]}"),
+        ("env", "--env A=1", "{environment: [A=1]}"),
+        ("e", "-e TERM", "{environment: [TERM]}"),
+        ("env-file", "--env-file .env", "{env_file: [.env]}"),
+        ("expose", "--expose 9000", "{expose: ['9000']}"),
+        (
+            "gpus",
+            "--gpus all",
+            "{deploy: {resources: {reservations: {devices: [{driver: nvidia, count: all, capabilities: [gpu]}]}}}}",
+        ),
+        ("group-add", "--group-add audio", "{group_add: [audio]}"),
+        ("health-cmd", "--health-cmd 'curl -f x'", "{healthcheck: {test: [CMD-SHELL, curl -f x]}}"),
+        ("health-interval", "--health-interval 90s", "{healthcheck: {interval: 1m30s}}"),
+        ("health-retries", "--health-retries 3", "{healthcheck: {retries: 3}}"),
+        ("health-start-interval", "--health-start-interval 2s", "{healthcheck: {start_interval: 2s}}"),
+        ("health-start-period", "--health-start-period 1m", "{healthcheck: {start_period: 1m}}"),
+        ("health-timeout", "--health-timeout 5s", "{healthcheck: {timeout: 5s}}"),
+        ("help", "--help", "{}"),
+        ("hostname", "--hostname web", "{hostname: web}"),
+        ("h", "-h api", "{hostname: api}"),
+        ("init", "--init", "{init: true}"),
+        ("interactive", "--interactive", "{stdin_open: true}"),
+        ("i", "-i", "{stdin_open: true}"),
+        ("io-maxbandwidth", "--io-maxbandwidth 10m", "{}"),
+        ("io-maxiops", "--io-maxiops 100", "{}"),
+        ("ip", "--network net --ip 10.0.0.5", "{networks: {net: {ipv4_address: 10.0.0.5}}}"),
+        ("ip6", "--network net --ip6 fd00::5", "{networks: {net: {ipv6_address: 'fd00::5'}}}"),
+        ("ipc", "--ipc host", "{ipc: host}"),
+        ("isolation", "--isolation process", "{isolation: process}"),
+        ("kernel-memory", "--kernel-memory 50m", "{}"),
+        ("label", "--label a=1", "{labels: [a=1]}"),
+        ("l", "-l b=2", "{labels: [b=2]}"),
+        ("label-file", "--label-file ./labels", "{label_file: [./labels]}"),
+        ("link", "--link db:database", "{links: ['db:database']}"),
+        ("link-local-ip", "--network net --link-local-ip 169.254.0.5", "{networks: {net: {link_local_ips: [169.254.0.5]}}}"),
+        ("log-driver", "--log-driver syslog", "{logging: {driver: syslog}}"),
+        ("log-opt", "--log-opt labels=a,b --log-opt max-file=3", "{logging: {options: {labels: 'a,b', max-file: 3}}}"),
+        ("mac-address", "--mac-address 02:42:ac:11:00:02", "{mac_address: '02:42:ac:11:00:02'}"),
+        ("memory", "--memory 512m", "{deploy: {resources: {limits: {memory: 512m}}}}"),
+        ("m", "-m 1g", "{deploy: {resources: {limits: {memory: 1g}}}}"),
+        ("memory-reservation", "--memory-reservation 256m", "{deploy: {resources: {reservations: {memory: 256m}}}}"),
+        ("memory-swap", "--memory-swap 2g", "{memswap_limit: 2g}"),
+        ("memory-swappiness", "--memory-swappiness 0", "{mem_swappiness: 0}"),
+        ("mount", "--mount type=bind,source=/a,target=/b", "{volumes: ['/a:/b']}"),
+        ("name", "--name web", "{container_name: web}"),
+        ("network", "--network backend", "{networks: {backend: {}}}"),
+        ("net", "--net host", "{network_mode: host}"),
+        ("network-alias", "--network net --network-alias api", "{networks: {net: {aliases: [api]}}}"),
+        ("net-alias", "--network net --net-alias web", "{networks: {net: {aliases: [web]}}}"),
+        ("no-healthcheck", "--no-healthcheck", "{healthcheck: {disable: true}}"),
+        ("oom-kill-disable", "--oom-kill-disable", "{oom_kill_disable: true}"),
+        ("oom-score-adj", "--oom-score-adj -500", "{oom_score_adj: -500}"),
+        ("pid", "--pid host", "{pid: host}"),
+        ("pids-limit", "--pids-limit 100", "{deploy: {resources: {limits: {pids: 100}}}}"),
+        ("platform", "--platform linux/arm64", "{platform: linux/arm64}"),
+        ("privileged", "--privileged", "{privileged: true}"),
+        ("publish", "--publish 80:80", "{ports: ['80:80']}"),
+        ("p", "-p 443:443", "{ports: ['443:443']}"),
+        ("publish-all", "--publish-all", "{}"),
+        ("P", "-P", "{}"),
+        ("pull", "--pull always", "{pull_policy: always}"),
+        ("quiet", "--quiet", "{}"),
+        ("q", "-q", "{}"),
+        ("read-only", "--read-only", "{read_only: true}"),
+        ("restart", "--restart unless-stopped", "{restart: unless-stopped}"),
+        ("rm", "--rm", "{}"),
+        ("runtime", "--runtime runsc", "{runtime: runsc}"),
+        ("security-opt", "--security-opt no-new-privileges", "{security_opt: [no-new-privileges]}"),
+        ("shm-size", "--shm-size 1g", "{shm_size: 1g}"),
+        ("sig-proxy", "--sig-proxy=false", "{}"),
+        ("stop-signal", "--stop-signal SIGINT", "{stop_signal: SIGINT}"),
+        ("stop-timeout", "--stop-timeout 30", "{stop_grace_period: 30s}"),
+        ("storage-opt", "--storage-opt size=10G", "{storage_opt: {size: 10G}}"),
+        ("sysctl", "--sysctl net.core.somaxconn=1024", "{sysctls: [net.core.somaxconn=1024]}"),
+        ("tmpfs", "--tmpfs /run", "{tmpfs: [/run]}"),
+        ("tty", "--tty", "{tty: true}"),
+        ("t", "-t", "{tty: true}"),
+        ("ulimit", "--ulimit nofile=1024:2048", "{ulimits: {nofile: {soft: 1024, hard: 2048}}}"),
+        ("use-api-socket", "--use-api-socket", "{use_api_socket: true}"),
+        ("user", "--user 1000:1000", "{user: '1000:1000'}"),
+        ("u", "-u app", "{user: app}"),
+        ("userns", "--userns host", "{userns_mode: host}"),
+        ("uts", "--uts host", "{uts: host}"),
+        ("volume", "--volume data:/data", "{volumes: ['data:/data']}"),
+        ("v", "-v /a:/b:ro", "{volumes: ['/a:/b:ro']}"),
+        ("volume-driver", "--volume-driver local", "{volume_driver: local}"),
+        ("volumes-from", "--volumes-from db", "{volumes_from: [db]}"),
+        ("workdir", "--workdir /app", "{working_dir: /app}"),
+        ("w", "-w /src", "{working_dir: /src}"),
+    ];
+
+    for flag in DOCKER_RUN_FLAGS {
+        assert!(cases.iter().any(|(name, _, _)| name == flag), "no test case for --{}", flag);
+    }
+    for (flag, args, expected) in cases {
+        let input = format!("docker run {} nginx", args);
+        let conversion = composerize_np::Composerizer::default()
+            .convert(&input)
+            .unwrap_or_else(|e| panic!("--{}: {}", flag, e));
+        // Flags that change the container but cannot be expressed must not vanish silently
+        let unsupported = get_mappings()[*flag].arg_type == ArgType::Unsupported;
+        let warned = conversion.warnings.iter().any(|w| w.ends_with("is not supported by compose"));
+        assert_eq!(warned, unsupported, "--{}: {:?}", flag, conversion.warnings);
+        let compose: serde_yaml::Value = serde_yaml::from_str(&conversion.output).unwrap();
+        let services = compose["services"].as_mapping().unwrap();
+        let mut service = services.values().next().unwrap().as_mapping().unwrap().clone();
+        service.remove("image");
+        let expected: serde_yaml::Value = serde_yaml::from_str(expected).unwrap();
+        assert_eq!(serde_yaml::Value::Mapping(service), expected, "--{}", flag);
+    }
+}
+
+#[test]
+fn test_gpu_requests_and_cdi_devices() {
+    let devices = |input: &str| {
+        let compose: serde_yaml::Value = serde_yaml::from_str(&composerize(input, "", "latest", 2).unwrap()).unwrap();
+        compose["services"]["cuda"]["deploy"]["resources"]["reservations"]["devices"].clone()
+    };
+    let yaml = |s: &str| serde_yaml::from_str::<serde_yaml::Value>(s).unwrap();
+
+    assert_eq!(
+        devices(r#"docker run --gpus '"device=0,2"' cuda"#),
+        yaml("[{driver: nvidia, device_ids: ['0', '2'], capabilities: [gpu]}]")
+    );
+    assert_eq!(
+        devices("docker run --gpus count=2,capabilities=compute,utility cuda"),
+        yaml("[{driver: nvidia, count: 2, capabilities: [compute, utility, gpu]}]")
+    );
+    assert_eq!(
+        devices("docker run --device nvidia.com/gpu=all --device /dev/fuse cuda"),
+        yaml("[{driver: cdi, device_ids: [nvidia.com/gpu=all], capabilities: [gpu]}]")
+    );
+
+    let output = composerize("docker run --device nvidia.com/gpu=all --device /dev/fuse cuda", "", "latest", 2).unwrap();
+    assert!(output.contains("devices:\n    - /dev/fuse"));
+
+    let error = composerize("docker run --gpus count=two cuda", "", "latest", 2).unwrap_err();
+    assert!(error.contains("Invalid GPU count 'two'"));
+}
+
+/// Runs the command-line tool in `dir` with no input on stdin
+fn run_cli(args: &[&str], dir: &std::path::Path) -> std::process::Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_composerize-np"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_cli_explain_before_command() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let output = run_cli(&["--explain", "docker run -p 80:80 nginx"], dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("-p 80:80"), "{}", stdout);
+    assert!(!stdout.contains("services:"));
+
+    let output = run_cli(&["--explain=json", "docker run -p 80:80 nginx"], dir);
+    assert!(output.status.success());
+    assert!(serde_json::from_slice::<serde_json::Value>(&output.stdout).is_ok());
+}
+
+#[test]
+fn test_cli_relative_binds_default_directory() {
+    let dir = std::path::absolute(env!("CARGO_TARGET_TMPDIR")).unwrap();
+    let command = format!("docker run -v {}/config:/etc/app:ro nginx", dir.display());
+    let output = run_cli(&["--relative-binds", &command], &dir);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("- ./config:/etc/app:ro"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("to ./config"));
+}